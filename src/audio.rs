@@ -1,12 +1,16 @@
 use anyhow::{Context as anyhowContext, Ok, Result};
-use serenity::{client::Context, model::channel::Message};
+use serenity::client::Context;
+use serenity::model::{id::ChannelId, voice::VoiceState};
 use songbird::tracks::TrackHandle;
 use songbird::Songbird;
 use std::fmt::Debug;
 use std::{ffi::OsStr, path::Path, sync::Arc};
 use tracing::info;
 
-use crate::utils::check_message;
+use crate::commands::Invocation;
+
+// O base path deve ser relativo ao current_dir, nao me pergunte pq
+const LOCAL_AUDIO_DIR: &str = "./assets/";
 
 pub async fn get_songbird_manager(ctx: &Context) -> Result<Arc<Songbird>> {
   let manager = songbird::get(ctx)
@@ -16,20 +20,31 @@ pub async fn get_songbird_manager(ctx: &Context) -> Result<Arc<Songbird>> {
   Ok(manager)
 }
 
-async fn join_channel(ctx: &Context, msg: &Message) -> Result<()> {
+/// Returns the voice state of the user that invoked the command.
+pub fn user_voice_state(ctx: &Context, invocation: &Invocation) -> Option<VoiceState> {
+  invocation
+    .guild_id()
+    .and_then(|guild_id| guild_id.to_guild_cached(ctx))
+    .and_then(|guild| guild.voice_states.get(&invocation.author().id).cloned())
+}
+
+/// Returns the voice channel the user that invoked the command is in.
+fn user_voice_channel(ctx: &Context, invocation: &Invocation) -> Option<ChannelId> {
+  user_voice_state(ctx, invocation)?.channel_id
+}
+
+async fn join_channel(ctx: &Context, invocation: &Invocation) -> Result<()> {
   let manager = get_songbird_manager(ctx).await?;
 
-  let guild = msg.guild(ctx).context("Failed to get guild")?;
+  let guild = invocation
+    .guild_id()
+    .and_then(|guild_id| guild_id.to_guild_cached(ctx))
+    .context("Failed to get guild")?;
   let guild_id = guild.id;
 
-  let user_voice_channel_id = guild
-    .voice_states
-    .get(&msg.author.id)
-    .and_then(|vs| vs.channel_id);
-
-  let user_voice_channel_id = match user_voice_channel_id {
+  let user_voice_channel_id = match user_voice_channel(ctx, invocation) {
     None => {
-      check_message(msg.reply(ctx, "tu nao ta em call dog").await);
+      invocation.reply(ctx, "tu nao ta em call dog").await?;
       return Ok(());
     }
     Some(channel_id) => channel_id,
@@ -42,7 +57,7 @@ async fn join_channel(ctx: &Context, msg: &Message) -> Result<()> {
 
   if let Some(bot_voice_channel_id) = bot_voice_channel_id {
     if bot_voice_channel_id != user_voice_channel_id {
-      check_message(msg.reply(ctx, "ja to em outra call dog").await);
+      invocation.reply(ctx, "ja to em outra call dog").await?;
       return Ok(());
     }
   }
@@ -57,15 +72,14 @@ async fn join_channel(ctx: &Context, msg: &Message) -> Result<()> {
 #[tracing::instrument(skip_all, fields(link = ?link))]
 pub async fn play_audio<P: AsRef<OsStr> + Debug>(
   ctx: &Context,
-  msg: &Message,
+  invocation: &Invocation,
   link: P,
 ) -> Result<TrackHandle> {
-  join_channel(ctx, msg).await?;
+  join_channel(ctx, invocation).await?;
 
   let manager = get_songbird_manager(ctx).await?;
 
-  let guild = msg.guild(ctx).context("Failed to get guild")?;
-  let guild_id = guild.id;
+  let guild_id = invocation.guild_id().context("Failed to get guild")?;
 
   let guild_lock = manager.get(guild_id).context("Unable to get guild lock")?;
 
@@ -84,64 +98,38 @@ pub async fn play_audio<P: AsRef<OsStr> + Debug>(
   Ok(track_handle)
 }
 
-pub async fn play_local_audio(ctx: &Context, msg: &Message, file_name: &str) -> Result<()> {
-  // O base path deve ser relativo ao current_dir, nao me pergunte pq
-  let file_path = "./assets/".to_owned() + file_name;
+/// Returns None if there's no file named `file_name`.
+pub async fn play_local_audio(
+  ctx: &Context,
+  invocation: &Invocation,
+  file_name: &str,
+) -> Result<Option<TrackHandle>> {
+  let file_path = LOCAL_AUDIO_DIR.to_owned() + file_name;
   let file_path = Path::new(&file_path);
 
   if !file_path.exists() {
-    check_message(msg.reply(ctx, "Esse arquivo nao existe").await);
-    return Ok(());
+    invocation.reply(ctx, "Esse arquivo nao existe").await?;
+    return Ok(None);
   }
 
-  let _ = play_audio(ctx, msg, file_path).await?;
+  let track_handle = play_audio(ctx, invocation, file_path).await?;
 
-  Ok(())
+  Ok(Some(track_handle))
 }
 
-pub async fn handler(ctx: &Context, msg: &Message, args_vec: Vec<&str>) -> Result<()> {
-  let mut args = args_vec.into_iter();
+/// Returns the names of the files that can be played with [play_local_audio].
+pub async fn local_audio_files() -> Result<Vec<String>> {
+  let mut entries = tokio::fs::read_dir(LOCAL_AUDIO_DIR).await?;
 
-  let sub_command = match args.next() {
-    None => {
-      check_message(msg.reply(ctx, "Tu nao passou um subcomando").await);
-      return Ok(());
-    }
-    Some(v) => v,
-  };
+  let mut file_names = vec![];
 
-  match sub_command {
-    "playlink" => {
-      let link = args.next();
-
-      match link {
-        Some(link) => {
-          play_audio(ctx, msg, link).await?;
-          Ok(())
-        }
-        None => {
-          check_message(msg.reply(ctx, "Faltou o link ae dog").await);
-          Ok(())
-        }
-      }
-    }
-    "playlocal" => {
-      let file_name = args.next();
-
-      match file_name {
-        Some(file_name) => {
-          play_local_audio(ctx, msg, file_name).await?;
-          Ok(())
-        }
-        None => {
-          check_message(msg.reply(ctx, "Faltou o nome do arquivo ae dog").await);
-          Ok(())
-        }
-      }
-    }
-    _ => {
-      check_message(msg.reply(ctx, "Command not found").await);
-      Ok(())
+  while let Some(entry) = entries.next_entry().await? {
+    if entry.file_type().await?.is_file() {
+      file_names.push(entry.file_name().to_string_lossy().to_string());
     }
   }
+
+  file_names.sort();
+
+  Ok(file_names)
 }
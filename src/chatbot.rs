@@ -1,66 +1,197 @@
-use std::{
-  collections::HashSet,
-  fmt::Write,
-  sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
-  },
-  time::Duration,
-};
+use std::{collections::HashMap, ffi::OsStr, sync::Arc, time::Duration};
 
 use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use serenity::{
   client::Context,
-  model::{channel::Message, id::ChannelId},
+  model::{
+    channel::Message,
+    id::{ChannelId, GuildId, UserId},
+  },
 };
 
 use tokio::sync::mpsc::{Receiver, Sender};
-use tokio::sync::RwLock;
-use tracing::{error, info};
+use tokio::sync::{Notify, RwLock};
+use tracing::{error, info, warn};
 
 use crate::{
-  audio, contracts, text_generation::TextGenerator, translation::Translation, utils::env_key,
+  audio,
+  commands::Invocation,
+  config,
+  contracts::{self, tts::AudioSource},
+  conversation::{self, Conversation, Speaker, Turn},
+  guild_settings::GuildSettingsStore,
+  language::{self, MODEL_LANGUAGE},
+  mentions::{self, Mention},
+  persona::{Persona, PersonaError, PersonaStore},
+  rate_limit::{self, RateLimiter},
+  text_generation::{Backend, Parameter, Profile, TextGenerator, USER_NAME},
+  translation::{self, Translation},
+  tts::{self, voice::VoiceStore},
 };
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// The conversation users start with when talking to the default persona.
+  pub initial_context: String,
+  /// The persona users talk to until they choose another one.
+  pub default_persona: String,
+  pub personas: Vec<Persona>,
+  pub memory: conversation::Config,
+  /// The conversation mode of the channels the chatbot joins.
+  pub conversation_mode: conversation::Mode,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      initial_context: String::new(),
+      default_persona: String::from("Eliza"),
+      personas: Vec::new(),
+      memory: conversation::Config::default(),
+      conversation_mode: conversation::Mode::default(),
+    }
+  }
+}
+
+impl Config {
+  /// Returns the personas in the config. The default persona starts
+  /// with `initial_context` unless it is also in `personas`.
+  pub fn personas(&self) -> Vec<Persona> {
+    let mut personas = self.personas.clone();
+
+    if !self.defines_default_persona() {
+      personas.push(Persona {
+        name: self.default_persona.clone(),
+        initial_context: self.initial_context.clone(),
+        ..Default::default()
+      });
+    }
+
+    personas
+  }
+
+  pub fn defines_default_persona(&self) -> bool {
+    self
+      .personas
+      .iter()
+      .any(|persona| persona.name.to_lowercase() == self.default_persona.to_lowercase())
+  }
+}
+
 pub struct ChatBot {
-  /// The set of text channels that the bot will interact with messages.
-  text_channels: RwLock<HashSet<ChannelId>>,
-  /// Will the bot reply to messages by playing audio?
-  voice_chat_enabled: AtomicBool,
+  config: std::sync::RwLock<Config>,
+  /// The text channels that the bot will interact with messages.
+  text_channels: RwLock<HashMap<ChannelId, TextChannel>>,
   /// Push a message into this channel to play it in the voice chat.
   voice_chat_reply_sender: Sender<VoiceChatReply>,
   _voice_chat_reply_thread_handle: tokio::task::JoinHandle<()>,
   tts: Arc<dyn contracts::tts::TextToSpeech>,
+  voices: VoiceStore,
   text_generator: TextGenerator,
   translation: Translation,
   cache: Arc<dyn contracts::cache::Cache>,
+  settings: Arc<GuildSettingsStore>,
+  personas: PersonaStore,
+  rate_limiter: Arc<RateLimiter>,
+}
+
+/// What the chatbot is doing in a guild, shown by the status command.
+#[derive(Debug)]
+pub struct Status {
+  pub backend: Backend,
+  /// The persona the user is talking to.
+  pub persona: String,
+  /// The conversation mode of the channel, `None` if the chatbot has not joined it.
+  pub conversation_mode: Option<conversation::Mode>,
+  pub profile: Profile,
+  /// The parameters of `profile` that were changed by the guild.
+  pub changed_parameters: Vec<Parameter>,
+  pub text_channels: Vec<ChannelId>,
+  pub voice_enabled: bool,
+  /// The languages the answers are shown in, see [USER_LANGUAGE].
+  pub answer_languages: Vec<String>,
+  pub translation_cache: translation::CacheStats,
+}
+
+/// A text channel the chatbot has joined, persisted so the chatbot
+/// keeps replying in the channel after the bot restarts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct TextChannel {
+  channel_id: ChannelId,
+  guild_id: Option<GuildId>,
+  #[serde(default)]
+  mode: conversation::Mode,
+}
+
+/// Identifies a stored conversation, users have their own conversation
+/// unless the channel is in [conversation::Mode::Channel].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversationKey {
+  User(UserId),
+  Channel(ChannelId),
+}
+
+impl ConversationKey {
+  fn cache_key(self) -> Vec<u8> {
+    match self {
+      ConversationKey::User(user_id) => format!("chatbot:history:{}", user_id).into_bytes(),
+      ConversationKey::Channel(channel_id) => {
+        format!("chatbot:history:channel:{}", channel_id).into_bytes()
+      }
+    }
+  }
 }
 
+const TEXT_CHANNELS_CACHE_KEY: &[u8] = b"chatbot:text_channels";
+
+/// Stands for the language the user wrote in when configuring the answer languages.
+pub const USER_LANGUAGE: &str = "user";
+
 /// The maximum number of voice channel voice messages that can be in the queue.
 const MAX_VOICE_CHAT_REPLY_QUEUE_LENGTH: usize = 256;
 
+/// How long to wait for audio that does not say how long it is to end.
+const MAX_VOICE_CHAT_AUDIO_WAIT: Duration = Duration::from_secs(5 * 60);
+
 struct VoiceChatReply {
-  audio_file_urls: Vec<String>,
+  audio: Vec<AudioSource>,
   ctx: Context,
-  msg: Message,
+  invocation: Invocation,
+}
+
+/// Wakes up the voice chat reply queue when the audio it is playing ends.
+struct TrackEndNotifier(Arc<Notify>);
+
+#[async_trait]
+impl songbird::EventHandler for TrackEndNotifier {
+  async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+    self.0.notify_one();
+    None
+  }
 }
 
 impl std::fmt::Debug for VoiceChatReply {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     f.debug_struct("VoiceChatReply")
-      .field("audio_file_urls", &self.audio_file_urls)
+      .field("audio", &self.audio)
       .field("ctx", &"DOES NOT IMPLEMENT DEBUG")
-      .field("msg", &self.msg)
+      .field("invocation", &"DOES NOT IMPLEMENT DEBUG")
       .finish()
   }
 }
 
 impl ChatBot {
   pub fn new(
+    config: Config,
     tts: Arc<dyn contracts::tts::TextToSpeech>,
     text_generator: TextGenerator,
     translation: Translation,
     cache: Arc<dyn contracts::cache::Cache>,
+    settings: Arc<GuildSettingsStore>,
+    rate_limiter: Arc<RateLimiter>,
   ) -> Self {
     let (sender, receiver) = tokio::sync::mpsc::channel(MAX_VOICE_CHAT_REPLY_QUEUE_LENGTH);
 
@@ -68,38 +199,394 @@ impl ChatBot {
     let handle = tokio::spawn(ChatBot::send_voice_chat_reply(receiver));
 
     Self {
+      config: std::sync::RwLock::new(config),
       tts,
       text_generator,
       translation,
-      text_channels: RwLock::new(HashSet::new()),
+      text_channels: RwLock::new(HashMap::new()),
       _voice_chat_reply_thread_handle: handle,
       voice_chat_reply_sender: sender,
-      voice_chat_enabled: AtomicBool::new(true),
+      personas: PersonaStore::new(Arc::clone(&cache)),
+      voices: VoiceStore::new(Arc::clone(&cache), Arc::clone(&settings)),
       cache,
+      settings,
+      rate_limiter,
     }
   }
 
+  /// Changes if the bot replies to messages in the guild by playing audio.
   #[tracing::instrument(skip_all)]
-  pub fn enable_voice(&self) {
-    self.voice_chat_enabled.store(true, Ordering::Relaxed);
+  pub async fn set_voice_enabled(&self, guild_id: GuildId, enabled: bool) -> Result<()> {
+    self
+      .settings
+      .update(guild_id, |settings| settings.voice_chat_disabled = !enabled)
+      .await?;
+
+    Ok(())
   }
 
+  /// Will the bot reply to messages in the guild by playing audio?
   #[tracing::instrument(skip_all)]
-  pub fn disable_voice(&self) {
-    self.voice_chat_enabled.store(false, Ordering::Relaxed);
+  pub async fn is_voice_enabled(&self, guild_id: Option<GuildId>) -> Result<bool> {
+    match guild_id {
+      None => Ok(true),
+      Some(guild_id) => Ok(!self.settings.get(guild_id).await?.voice_chat_disabled),
+    }
+  }
+
+  /// Reads the text in the voice channel of the user that invoked the command,
+  /// after the replies that are already waiting to be played. The voice the user chose
+  /// is used when `voice` is None.
+  #[tracing::instrument(name = "ChatBot::say", skip_all)]
+  pub async fn say(
+    &self,
+    ctx: &Context,
+    invocation: &Invocation,
+    text: &str,
+    voice: Option<String>,
+  ) -> Result<()> {
+    let voice = match voice {
+      Some(voice) => Some(voice),
+      None => {
+        self
+          .voices
+          .voice(invocation.guild_id(), invocation.author().id, None)
+          .await?
+      }
+    };
+
+    let audio = self
+      .tts
+      .create_audio(remove_links_from_text(text), voice)
+      .await?;
+
+    self
+      .voice_chat_reply_sender
+      .send(VoiceChatReply {
+        ctx: ctx.clone(),
+        invocation: invocation.clone(),
+        audio,
+      })
+      .await?;
+
+    Ok(())
+  }
+
+  fn config(&self) -> Config {
+    self.config.read().unwrap().clone()
+  }
+
+  /// Returns the sampling parameters used by the persona in the guild.
+  /// The parameters changed by the guild replace the ones set by the persona.
+  pub async fn generation_profile(
+    &self,
+    guild_id: Option<GuildId>,
+    persona: &Persona,
+  ) -> Result<Profile> {
+    let profile = self
+      .text_generator
+      .default_profile()
+      .with_overrides(&persona.profile);
+
+    Ok(match guild_id {
+      None => profile,
+      Some(guild_id) => profile.with_overrides(&self.settings.get(guild_id).await?.generation),
+    })
+  }
+
+  /// Changes a sampling parameter used in the guild, `None` goes back to the default value.
+  /// The value must have been validated with [Parameter::validate].
+  #[tracing::instrument(skip_all, fields(parameter = %parameter))]
+  pub async fn set_generation_parameter(
+    &self,
+    guild_id: GuildId,
+    parameter: Parameter,
+    value: Option<f32>,
+  ) -> Result<()> {
+    self
+      .settings
+      .update(guild_id, |settings| match value {
+        None => {
+          settings.generation.remove(&parameter);
+        }
+        Some(value) => {
+          settings.generation.insert(parameter, value);
+        }
+      })
+      .await?;
+
+    Ok(())
+  }
+
+  pub async fn status(
+    &self,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    user_id: UserId,
+  ) -> Result<Status> {
+    let key = self.conversation_key(channel_id, user_id).await;
+    let persona = self.conversation_persona(key).await?;
+
+    let changed_parameters = match guild_id {
+      None => Vec::new(),
+      Some(guild_id) => self
+        .settings
+        .get(guild_id)
+        .await?
+        .generation
+        .into_keys()
+        .collect(),
+    };
+
+    Ok(Status {
+      backend: self.text_generator.backend_for(guild_id),
+      profile: self.generation_profile(guild_id, &persona).await?,
+      persona: persona.name,
+      conversation_mode: self.conversation_mode(channel_id).await,
+      changed_parameters,
+      text_channels: self.text_channels(guild_id).await,
+      voice_enabled: self.is_voice_enabled(guild_id).await?,
+      answer_languages: match self.answer_language_settings(guild_id).await? {
+        languages if languages.is_empty() => {
+          vec![MODEL_LANGUAGE.to_owned(), USER_LANGUAGE.to_owned()]
+        }
+        languages => languages,
+      },
+      translation_cache: self.translation.cache_stats(),
+    })
   }
 
+  pub async fn personas(&self) -> Result<Vec<Persona>> {
+    self.personas.list(&self.config()).await
+  }
+
+  /// Returns the persona the user is talking to.
+  pub async fn persona(&self, user_id: UserId) -> Result<Persona> {
+    self.personas.selected(&self.config(), user_id).await
+  }
+
+  /// Returns the persona of the conversation, channel conversations use the default persona.
+  pub async fn conversation_persona(&self, key: ConversationKey) -> Result<Persona> {
+    match key {
+      ConversationKey::User(user_id) => self.persona(user_id).await,
+      ConversationKey::Channel(_) => self.personas.default_persona(&self.config()).await,
+    }
+  }
+
+  /// Makes the user talk to another persona, the conversation starts over.
+  #[tracing::instrument(name = "ChatBot::select_persona", skip_all, fields(user_id = %user_id))]
+  pub async fn select_persona(&self, user_id: UserId, name: &str) -> Result<Persona> {
+    let persona = self.personas.select(&self.config(), user_id, name).await?;
+
+    self
+      .cache_conversation(
+        ConversationKey::User(user_id),
+        &Conversation::new(&persona.initial_context, &persona.name),
+      )
+      .await?;
+
+    Ok(persona)
+  }
+
+  pub async fn create_persona(&self, persona: Persona) -> Result<()> {
+    self.personas.create(&self.config(), persona).await
+  }
+
+  pub async fn edit_persona(
+    &self,
+    name: &str,
+    field: &str,
+    value: Option<&str>,
+  ) -> Result<Persona> {
+    // The voice is kept the way the engine names it.
+    let value = match (field, value) {
+      ("voice", Some(voice)) => Some(
+        tts::find_voice(self.tts.as_ref(), voice)
+          .await
+          .ok_or_else(|| PersonaError::UnknownVoice(voice.to_owned()))?,
+      ),
+      (_, value) => value.map(String::from),
+    };
+
+    self
+      .personas
+      .edit(&self.config(), name, field, value.as_deref())
+      .await
+  }
+
+  pub async fn delete_persona(&self, name: &str) -> Result<()> {
+    self.personas.delete(&self.config(), name).await
+  }
+
+  /// Adds the bot the text channel where the command has been invoked.
   #[tracing::instrument(skip_all)]
-  pub fn is_voice_enabled(&self) -> bool {
-    self.voice_chat_enabled.load(Ordering::Relaxed)
+  pub async fn join_text_channel(&self, ctx: &Context, invocation: &Invocation) -> Result<()> {
+    let joined = self
+      .add_text_channel(invocation.channel_id(), invocation.guild_id())
+      .await?;
+
+    let reply = if joined {
+      "chatbot joined the channel"
+    } else {
+      "chatbot is already in the channel"
+    };
+
+    invocation.reply(ctx, reply).await?;
+
+    Ok(())
   }
 
-  /// Adds the bot the text channel where the message has been sent to.
+  /// Removes the bot from the text channel where the command has been invoked.
   #[tracing::instrument(skip_all)]
-  pub async fn join_text_channel(&self, ctx: &Context, msg: &Message) -> Result<()> {
-    self.text_channels.write().await.insert(msg.channel_id);
+  pub async fn leave_text_channel(&self, ctx: &Context, invocation: &Invocation) -> Result<()> {
+    let left = self.remove_text_channel(invocation.channel_id()).await?;
 
-    msg.reply(ctx, "chatbot joined the channel").await?;
+    let reply = if left {
+      "chatbot left the channel"
+    } else {
+      "chatbot is not in the channel"
+    };
+
+    invocation.reply(ctx, reply).await?;
+
+    Ok(())
+  }
+
+  /// Returns the text channels the chatbot has joined in the guild.
+  pub async fn text_channels(&self, guild_id: Option<GuildId>) -> Vec<ChannelId> {
+    let mut channels: Vec<ChannelId> = self
+      .text_channels
+      .read()
+      .await
+      .values()
+      .filter(|text_channel| text_channel.guild_id == guild_id)
+      .map(|text_channel| text_channel.channel_id)
+      .collect();
+
+    channels.sort();
+
+    channels
+  }
+
+  /// Returns false if the chatbot was already in the channel.
+  #[tracing::instrument(name = "ChatBot::add_text_channel", skip_all, fields(channel_id = %channel_id))]
+  async fn add_text_channel(
+    &self,
+    channel_id: ChannelId,
+    guild_id: Option<GuildId>,
+  ) -> Result<bool> {
+    let mut text_channels = self.text_channels.write().await;
+
+    if text_channels.contains_key(&channel_id) {
+      return Ok(false);
+    }
+
+    // The channel is only joined once it has been persisted so memory and the cache do not diverge.
+    let mut updated = text_channels.clone();
+    updated.insert(
+      channel_id,
+      TextChannel {
+        channel_id,
+        guild_id,
+        mode: self.config().conversation_mode,
+      },
+    );
+
+    self.persist_text_channels(&updated).await?;
+
+    *text_channels = updated;
+
+    Ok(true)
+  }
+
+  /// Returns the conversation mode of the channel, `None` if the chatbot has not joined it.
+  pub async fn conversation_mode(&self, channel_id: ChannelId) -> Option<conversation::Mode> {
+    self
+      .text_channels
+      .read()
+      .await
+      .get(&channel_id)
+      .map(|text_channel| text_channel.mode)
+  }
+
+  /// Changes the conversation mode of the channel. Returns false if the chatbot has not joined it.
+  #[tracing::instrument(name = "ChatBot::set_conversation_mode", skip_all, fields(channel_id = %channel_id, mode = %mode))]
+  pub async fn set_conversation_mode(
+    &self,
+    channel_id: ChannelId,
+    mode: conversation::Mode,
+  ) -> Result<bool> {
+    let mut text_channels = self.text_channels.write().await;
+
+    let mut updated = text_channels.clone();
+    match updated.get_mut(&channel_id) {
+      None => return Ok(false),
+      Some(text_channel) => text_channel.mode = mode,
+    }
+
+    self.persist_text_channels(&updated).await?;
+
+    *text_channels = updated;
+
+    Ok(true)
+  }
+
+  /// Returns the conversation the user takes part in when messaging in the channel.
+  pub async fn conversation_key(&self, channel_id: ChannelId, user_id: UserId) -> ConversationKey {
+    match self.conversation_mode(channel_id).await {
+      Some(conversation::Mode::Channel) => ConversationKey::Channel(channel_id),
+      _ => ConversationKey::User(user_id),
+    }
+  }
+
+  /// Returns false if the chatbot was not in the channel.
+  #[tracing::instrument(name = "ChatBot::remove_text_channel", skip_all, fields(channel_id = %channel_id))]
+  async fn remove_text_channel(&self, channel_id: ChannelId) -> Result<bool> {
+    let mut text_channels = self.text_channels.write().await;
+
+    let mut updated = text_channels.clone();
+    if updated.remove(&channel_id).is_none() {
+      return Ok(false);
+    }
+
+    self.persist_text_channels(&updated).await?;
+
+    *text_channels = updated;
+
+    Ok(true)
+  }
+
+  async fn persist_text_channels(
+    &self,
+    text_channels: &HashMap<ChannelId, TextChannel>,
+  ) -> Result<()> {
+    let text_channels: Vec<TextChannel> = text_channels.values().copied().collect();
+
+    self
+      .cache
+      .put(
+        TEXT_CHANNELS_CACHE_KEY.to_vec(),
+        serde_json::to_vec(&text_channels)?,
+        None,
+      )
+      .await
+  }
+
+  /// Joins the text channels the chatbot was in before the bot restarted.
+  #[tracing::instrument(name = "ChatBot::restore_text_channels", skip_all)]
+  pub async fn restore_text_channels(&self) -> Result<()> {
+    let text_channels: Vec<TextChannel> = match self.cache.get(TEXT_CHANNELS_CACHE_KEY).await? {
+      None => return Ok(()),
+      Some(bytes) => serde_json::from_slice(&bytes)?,
+    };
+
+    info!("restoring text channels. count={}", text_channels.len());
+
+    self.text_channels.write().await.extend(
+      text_channels
+        .into_iter()
+        .map(|text_channel| (text_channel.channel_id, text_channel)),
+    );
 
     Ok(())
   }
@@ -126,72 +613,213 @@ impl ChatBot {
 
   #[tracing::instrument(name = "Chatbot::do_send_voice_chat_reply", skip_all)]
   async fn do_send_voice_chat_reply(message: VoiceChatReply) -> Result<()> {
-    for audio_file_chunk_url in message.audio_file_urls.into_iter() {
-      let track_handle =
-        audio::play_audio(&message.ctx, &message.msg, audio_file_chunk_url).await?;
+    for audio in message.audio.iter() {
+      let location = tts::audio_location(audio).await?;
 
-      let metadata = track_handle.metadata();
+      let result = Self::play_voice_chat_audio(&message, &location).await;
 
-      tokio::time::sleep(metadata.duration.unwrap() + Duration::from_millis(500)).await;
+      tts::remove_audio_file(&location).await;
+
+      result?;
     }
 
     Ok(())
   }
 
-  /// Ensure the text sent to the chat bot is not too long because the api may
-  /// get slow if it is.
-  #[tracing::instrument(name = "Chatbot::truncate_conversation_length", skip_all)]
-  fn truncate_conversation_length(&self, context: &mut String) {
-    // The api has a limit of 5000 characters but the chat bot gets slow at around 3500.
-    const MAX_CONVERSARTION_HISTORY_LEN: usize = 2750;
-    if context.len() > MAX_CONVERSARTION_HISTORY_LEN {
-      info!(
-        "pruning chat bot context. len_before_pruning={}",
-        context.len()
+  async fn play_voice_chat_audio(message: &VoiceChatReply, location: &OsStr) -> Result<()> {
+    let track_handle = audio::play_audio(&message.ctx, &message.invocation, location).await?;
+
+    if let Some(duration) = track_handle.metadata().duration {
+      tokio::time::sleep(duration + Duration::from_millis(500)).await;
+      return Ok(());
+    }
+
+    // Audio like the wav made by the local engines may not say how long it is.
+    info!("voice chat audio has no duration, waiting for it to end");
+
+    let ended = Arc::new(Notify::new());
+    track_handle.add_event(
+      songbird::Event::Track(songbird::TrackEvent::End),
+      TrackEndNotifier(Arc::clone(&ended)),
+    )?;
+
+    if tokio::time::timeout(MAX_VOICE_CHAT_AUDIO_WAIT, ended.notified())
+      .await
+      .is_err()
+    {
+      warn!(
+        "voice chat audio did not end in time. max_wait={:?}",
+        MAX_VOICE_CHAT_AUDIO_WAIT
       );
+    }
+
+    Ok(())
+  }
+
+  /// Ensure the text sent to the chat bot is not too long because the api may
+  /// get slow if it is. The old turns are summarized, or dropped when the summary
+  /// cannot be generated, and the persona context is always kept.
+  #[tracing::instrument(name = "Chatbot::fit_conversation", skip_all)]
+  async fn fit_conversation(
+    &self,
+    conversation: &mut Conversation,
+    guild_id: Option<GuildId>,
+    profile: &Profile,
+  ) {
+    let config = self.config().memory;
+
+    if conversation.tokens() <= config.max_tokens {
+      return;
+    }
 
-      *context = context
-        .chars()
-        .skip(context.len() - MAX_CONVERSARTION_HISTORY_LEN)
-        .take(MAX_CONVERSARTION_HISTORY_LEN)
-        .collect();
+    info!(
+      "pruning chat bot context. tokens_before_pruning={}",
+      conversation.tokens()
+    );
+
+    if config.summarize {
+      let old_turns = conversation.take_old_turns(config.keep_turns);
+
+      if !old_turns.is_empty() {
+        let prompt = conversation.summary_prompt(&old_turns);
+
+        match self
+          .text_generator
+          .generate(
+            guild_id,
+            &Conversation::new(&prompt, conversation::SUMMARY_SPEAKER),
+            profile,
+          )
+          .await
+        {
+          Err(err) => error!("unable to summarize conversation. error={:?}", err),
+          Ok(summary) => conversation.summary = Some(summary),
+        }
+      }
     }
+
+    conversation.drop_oldest_turns(config.max_tokens);
   }
 
-  #[tracing::instrument(skip_all, fields(user_id = %user_id))]
-  pub async fn conversation_history_for_user(&self, user_id: u64) -> Result<String> {
-    let conversation = self
-      .cache
-      .get(&user_id.to_le_bytes())
-      .await?
-      .map(|bytes| String::from_utf8_lossy(&bytes).to_string());
+  /// Returns the stored conversation, a new conversation with the persona if there is none.
+  #[tracing::instrument(skip_all, fields(key = ?key))]
+  pub async fn conversation(&self, key: ConversationKey) -> Result<Conversation> {
+    if let Some(bytes) = self.cache.get(&key.cache_key()).await? {
+      return Ok(serde_json::from_slice(&bytes)?);
+    }
+
+    let persona = self.conversation_persona(key).await?;
 
-    match conversation {
-      None => Ok(env_key("CHAIML_INITIAL_CONTEXT")?),
-      Some(conversation) => Ok(conversation),
+    // Conversations used to be stored as text under the user id.
+    if let ConversationKey::User(user_id) = key {
+      if let Some(bytes) = self.cache.get(&user_id.0.to_le_bytes()).await? {
+        let conversation = Conversation::parse(
+          &String::from_utf8_lossy(&bytes),
+          &persona.initial_context,
+          &persona.name,
+        );
+        // Stored as JSON the preamble stays apart from the turns even if the persona context changes.
+        self.cache_conversation(key, &conversation).await?;
+        return Ok(conversation);
+      }
+    }
+
+    Ok(Conversation::new(&persona.initial_context, &persona.name))
+  }
+
+  /// Makes the conversation start over from the initial context.
+  #[tracing::instrument(name = "Chatbot::reset_history", skip_all, fields(key = ?key))]
+  pub async fn reset_history(&self, key: ConversationKey) -> Result<()> {
+    self.forget_history(key).await?;
+
+    let persona = self.conversation_persona(key).await?;
+
+    self
+      .cache_conversation(
+        key,
+        &Conversation::new(&persona.initial_context, &persona.name),
+      )
+      .await
+  }
+
+  /// Deletes the stored conversation.
+  #[tracing::instrument(name = "Chatbot::forget_history", skip_all, fields(key = ?key))]
+  pub async fn forget_history(&self, key: ConversationKey) -> Result<()> {
+    self.cache.delete(&key.cache_key()).await?;
+
+    // The conversation stored as text before conversations were stored as JSON is deleted too.
+    if let ConversationKey::User(user_id) = key {
+      self.cache.delete(&user_id.0.to_le_bytes()).await?;
     }
+
+    Ok(())
   }
 
+  /// Replaces the conversation with one like `Me: hi\nEliza: hello`.
   #[tracing::instrument(name = "Chatbot::set_user_history", skip_all, fields(user_id = %user_id))]
-  pub async fn set_user_history(&self, user_id: u64, history: &str) -> Result<()> {
-    self.cache_user_conversation(user_id, history).await
+  pub async fn set_user_history(&self, user_id: UserId, history: &str) -> Result<()> {
+    let persona = self.persona(user_id).await?;
+
+    self
+      .cache_conversation(
+        ConversationKey::User(user_id),
+        &Conversation::parse(history, &persona.initial_context, &persona.name),
+      )
+      .await
   }
 
-  #[tracing::instrument(name = "ChatBot::cache_user_conversation", skip_all, fields(user_id = %user_id, conversation_len = %conversation.len()))]
-  async fn cache_user_conversation(&self, user_id: u64, conversation: &str) -> Result<()> {
+  #[tracing::instrument(name = "ChatBot::cache_conversation", skip_all, fields(key = ?key, turns = %conversation.turns.len()))]
+  async fn cache_conversation(
+    &self,
+    key: ConversationKey,
+    conversation: &Conversation,
+  ) -> Result<()> {
     self
       .cache
       .put(
-        user_id.to_le_bytes().to_vec(),
-        conversation.as_bytes().to_vec(),
+        key.cache_key(),
+        serde_json::to_vec(conversation)?,
         // 7 days
-        Duration::from_secs(60 * 60 * 24 * 7),
+        Some(Duration::from_secs(60 * 60 * 24 * 7)),
       )
       .await?;
 
     Ok(())
   }
 
+  /// Translates the text unless it is already in `to_lang`.
+  async fn translate(&self, text: &str, from_lang: &str, to_lang: &str) -> Result<String> {
+    if from_lang == to_lang {
+      return Ok(text.to_owned());
+    }
+
+    self.translation.translate(text, from_lang, to_lang).await
+  }
+
+  /// Returns the languages the guild shows the answers in, empty if the guild has not changed them.
+  async fn answer_language_settings(&self, guild_id: Option<GuildId>) -> Result<Vec<String>> {
+    Ok(match guild_id {
+      None => Vec::new(),
+      Some(guild_id) => self.settings.get(guild_id).await?.answer_languages,
+    })
+  }
+
+  /// Changes the languages the answers are shown in, empty goes back to the default languages.
+  /// [USER_LANGUAGE] stands for the language the user wrote in.
+  #[tracing::instrument(skip_all, fields(guild_id = %guild_id))]
+  pub async fn set_answer_languages(
+    &self,
+    guild_id: GuildId,
+    languages: Vec<String>,
+  ) -> Result<()> {
+    self
+      .settings
+      .update(guild_id, |settings| settings.answer_languages = languages)
+      .await?;
+
+    Ok(())
+  }
+
   /// Called whenever a message is sent.
   #[tracing::instrument(name = "ChatBot::on_message", skip_all)]
   pub async fn on_message(&self, ctx: &Context, msg: &Message) -> Result<()> {
@@ -200,59 +828,161 @@ impl ChatBot {
     }
 
     // User must use the `chatbot` command to enable the bot in the channel.
-    let text_channels = self.text_channels.read().await;
-    if !text_channels.contains(&msg.channel_id) {
+    let mode = match self.conversation_mode(msg.channel_id).await {
+      None => return Ok(()),
+      Some(mode) => mode,
+    };
+
+    // Every message costs a few api calls so stop spammers before any of them is made.
+    if let Err(throttled) = self
+      .rate_limiter
+      .check_message(msg.author.id, msg.channel_id)
+    {
+      info!("user is being rate limited. throttled={:?}", throttled);
+      if throttled.should_warn {
+        msg
+          .reply(ctx, rate_limit::wait_message(throttled.wait_time))
+          .await?;
+      }
       return Ok(());
     }
 
-    let message_in_english: String = self.translation.translate(&msg.content, "pt", "en").await?;
+    let key = match mode {
+      conversation::Mode::User => ConversationKey::User(msg.author.id),
+      conversation::Mode::Channel => ConversationKey::Channel(msg.channel_id),
+    };
 
-    let mut conversation = self.conversation_history_for_user(msg.author.id.0).await?;
+    let persona = self.conversation_persona(key).await?;
 
-    // Save the chat bot response so we can use it as context later.
-    writeln!(&mut conversation, "Me: {}", &message_in_english)?;
+    let author = match mode {
+      conversation::Mode::User => None,
+      conversation::Mode::Channel => {
+        let name = msg
+          .author_nick(ctx)
+          .await
+          .unwrap_or_else(|| msg.author.name.clone());
+        Some(speaker_label(&name, &persona.name))
+      }
+    };
+
+    let mut conversation = self.conversation(key).await?;
+
+    let content = resolve_mentions(ctx, msg);
 
-    self.truncate_conversation_length(&mut conversation);
+    // Short messages like "ok" cannot be detected, so use the language the user wrote in before.
+    let user_language = language::detect(&content)
+      .map(String::from)
+      .or_else(|| {
+        conversation
+          .turns
+          .iter()
+          .rev()
+          .find(|turn| turn.speaker == Speaker::User && turn.author == author)
+          .map(|turn| turn.language.clone())
+      })
+      .unwrap_or_else(|| persona.language.clone());
+
+    let message_in_english = self
+      .translate(&content, &user_language, MODEL_LANGUAGE)
+      .await?;
 
-    let bot_message_in_english = self.text_generator.generate(&conversation).await?;
+    conversation.push(Turn {
+      language: user_language.clone(),
+      timestamp: msg.timestamp,
+      message_id: Some(msg.id),
+      author,
+      ..Turn::new(Speaker::User, &message_in_english)
+    });
 
-    // Add bot response to context.
-    writeln!(&mut conversation, "Eliza: {}", &bot_message_in_english)?;
+    let profile = self.generation_profile(msg.guild_id, &persona).await?;
 
     self
-      .cache_user_conversation(msg.author.id.0, &conversation)
+      .fit_conversation(&mut conversation, msg.guild_id, &profile)
+      .await;
+
+    let bot_message_in_english = self
+      .text_generator
+      .generate(msg.guild_id, &conversation, &profile)
       .await?;
 
-    let bot_message_in_portuguese = self
-      .translation
-      .translate(&bot_message_in_english, "en", "pt")
+    let bot_message = self
+      .translate(&bot_message_in_english, MODEL_LANGUAGE, &user_language)
       .await?;
 
-    let answer = format!(
-      "EN:{} \n\nPT: {}",
-      bot_message_in_english, bot_message_in_portuguese
+    let mut translations = vec![
+      (MODEL_LANGUAGE.to_owned(), bot_message_in_english.clone()),
+      (user_language.clone(), bot_message.clone()),
+    ];
+    let languages = answer_languages(
+      &self.answer_language_settings(msg.guild_id).await?,
+      &user_language,
     );
-
-    if let Err(err) = msg.reply(ctx, &answer).await {
-      error!("error replying to message. error={:?}", err);
+    for language in languages.iter() {
+      if !translations.iter().any(|(lang, _)| lang == language) {
+        let text = self
+          .translate(&bot_message_in_english, MODEL_LANGUAGE, language)
+          .await?;
+        translations.push((language.clone(), text));
+      }
     }
 
-    if !self.is_voice_enabled() {
+    let users = mentionable_users(ctx, msg);
+    let answer = mentions::mention_members(&format_answer(&languages, &translations), &users);
+
+    // Only the users in the conversation are pinged, never everyone or roles the answer names.
+    let reply = msg
+      .channel_id
+      .send_message(&ctx.http, |message| {
+        message
+          .reference_message(msg)
+          .content(&answer)
+          .allowed_mentions(|mentions| {
+            mentions
+              .empty_parse()
+              .users(users.iter().map(|(user_id, _)| *user_id))
+              .replied_user(false)
+          })
+      })
+      .await;
+
+    let reply_message_id = match reply {
+      Err(err) => {
+        error!("error replying to message. error={:?}", err);
+        None
+      }
+      Ok(reply) => Some(reply.id),
+    };
+
+    // Save the chat bot response so we can use it as context later.
+    conversation.push(Turn {
+      language: user_language.clone(),
+      message_id: reply_message_id,
+      ..Turn::new(Speaker::Bot, &bot_message_in_english)
+    });
+
+    self.cache_conversation(key, &conversation).await?;
+
+    if !self.is_voice_enabled(msg.guild_id).await? {
       info!("voice chat is disabled");
       return Ok(());
     }
 
-    let audio_file_urls = self
+    let voice = self
+      .voices
+      .voice(msg.guild_id, msg.author.id, persona.voice)
+      .await?;
+
+    let audio = self
       .tts
-      .create_audio(remove_links_from_text(&bot_message_in_portuguese))
+      .create_audio(remove_links_from_text(&bot_message), voice)
       .await?;
 
     self
       .voice_chat_reply_sender
       .send(VoiceChatReply {
         ctx: ctx.clone(),
-        msg: msg.clone(),
-        audio_file_urls,
+        invocation: Invocation::from(msg.clone()),
+        audio,
       })
       .await?;
 
@@ -260,6 +990,136 @@ impl ChatBot {
   }
 }
 
+impl config::Reloadable for ChatBot {
+  fn reload(&self, config: &config::Config) {
+    *self.config.write().unwrap() = config.chatbot.clone();
+    self.text_generator.reload(config);
+    self.translation.reload(config);
+  }
+}
+
+/// Returns the languages the answer is shown in, in order and without repetitions.
+fn answer_languages(settings: &[String], user_language: &str) -> Vec<String> {
+  let default_languages = [MODEL_LANGUAGE.to_owned(), USER_LANGUAGE.to_owned()];
+  let settings = if settings.is_empty() {
+    &default_languages[..]
+  } else {
+    settings
+  };
+
+  let mut languages: Vec<String> = Vec::new();
+
+  for language in settings {
+    let language = if language == USER_LANGUAGE {
+      user_language
+    } else {
+      language
+    };
+
+    if !languages.iter().any(|lang| lang == language) {
+      languages.push(language.to_owned());
+    }
+  }
+
+  languages
+}
+
+/// Formats the answer like `EN: hello\n\nPT: olá`, a single language is shown without the prefix.
+fn format_answer(languages: &[String], translations: &[(String, String)]) -> String {
+  let texts: Vec<(&String, &String)> = languages
+    .iter()
+    .filter_map(|language| {
+      translations
+        .iter()
+        .find(|(lang, _)| lang == language)
+        .map(|(lang, text)| (lang, text))
+    })
+    .collect();
+
+  match texts.as_slice() {
+    [(_, text)] => (*text).clone(),
+    texts => texts
+      .iter()
+      .map(|(language, text)| format!("{}: {}", language.to_uppercase(), text))
+      .collect::<Vec<_>>()
+      .join("\n\n"),
+  }
+}
+
+/// Returns the message content with the mentions replaced by names.
+fn resolve_mentions(ctx: &Context, msg: &Message) -> String {
+  mentions::resolve(&msg.content, |mention| match mention {
+    Mention::User(user_id) => msg
+      .guild_id
+      .and_then(|guild_id| ctx.cache.member(guild_id, user_id))
+      .map(|member| member.display_name().into_owned())
+      .or_else(|| {
+        msg
+          .mentions
+          .iter()
+          .find(|user| user.id == user_id)
+          .map(|user| user.name.clone())
+      }),
+    Mention::Role(role_id) => msg
+      .guild_id
+      .and_then(|guild_id| ctx.cache.role(guild_id, role_id))
+      .map(|role| role.name),
+    Mention::Channel(channel_id) => ctx
+      .cache
+      .guild_channel(channel_id)
+      .map(|channel| channel.name),
+  })
+}
+
+/// Returns the users the reply can mention and their display names: the author
+/// and the users the message mentions.
+fn mentionable_users(ctx: &Context, msg: &Message) -> Vec<(UserId, String)> {
+  let author_name = msg
+    .member
+    .as_ref()
+    .and_then(|member| member.nick.clone())
+    .unwrap_or_else(|| msg.author.name.clone());
+
+  let mut users = vec![(msg.author.id, author_name)];
+
+  for user in msg.mentions.iter().filter(|user| !user.bot) {
+    if users.iter().any(|(user_id, _)| *user_id == user.id) {
+      continue;
+    }
+
+    let name = msg
+      .guild_id
+      .and_then(|guild_id| ctx.cache.member(guild_id, user.id))
+      .map(|member| member.display_name().into_owned())
+      .unwrap_or_else(|| user.name.clone());
+
+    users.push((user.id, name));
+  }
+
+  users
+}
+
+/// Returns the name a user is labelled with in a channel conversation.
+/// The conversation has a message per line like `name: message` so the name cannot break it,
+/// and users named like the bot are told apart so they cannot write turns as the bot.
+fn speaker_label(name: &str, bot_name: &str) -> String {
+  let label = name.replace([':', '\n', '\r'], " ").trim().to_owned();
+
+  if label.is_empty() {
+    return String::from("User");
+  }
+
+  let is_reserved = [bot_name, USER_NAME, conversation::SUMMARY_SPEAKER]
+    .iter()
+    .any(|reserved| label.eq_ignore_ascii_case(reserved));
+
+  if is_reserved {
+    format!("{} (user)", label)
+  } else {
+    label
+  }
+}
+
 fn remove_links_from_text(text: &str) -> String {
   text
     .split_whitespace()
@@ -270,7 +1130,191 @@ fn remove_links_from_text(text: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+  use std::sync::Mutex;
+
   use super::*;
+  use crate::{
+    contracts::{
+      cache::{self, MockCache},
+      http::MockHttpClient,
+      tts::MockTextToSpeech,
+    },
+    rate_limit, text_generation,
+  };
+
+  fn new_chatbot(cache: MockCache) -> ChatBot {
+    ChatBot::new(
+      Config::default(),
+      Arc::new(MockTextToSpeech::new()),
+      TextGenerator::new(
+        text_generation::Config::default(),
+        Arc::new(MockHttpClient::new()),
+      ),
+      Translation::new(
+        translation::Config::default(),
+        Arc::new(MockHttpClient::new()),
+        Arc::new(MockCache::new()),
+      ),
+      Arc::new(cache),
+      Arc::new(GuildSettingsStore::new(Arc::new(MockCache::new()))),
+      Arc::new(RateLimiter::new(rate_limit::Config::default())),
+    )
+  }
+
+  #[tokio::test]
+  async fn text_channels_are_restored_after_restarting() -> Result<()> {
+    let stored = Arc::new(Mutex::new(None));
+
+    let mut cache = MockCache::new();
+    let put_stored = Arc::clone(&stored);
+    cache
+      .expect_put()
+      .times(3)
+      .returning(move |key, value, ttl| {
+        assert_eq!(TEXT_CHANNELS_CACHE_KEY, key);
+        assert_eq!(None, ttl);
+        *put_stored.lock().unwrap() = Some(value);
+        Ok(())
+      });
+
+    let chatbot = new_chatbot(cache);
+    assert!(
+      chatbot
+        .add_text_channel(ChannelId(1), Some(GuildId(1)))
+        .await?
+    );
+    assert!(
+      !chatbot
+        .add_text_channel(ChannelId(1), Some(GuildId(1)))
+        .await?
+    );
+    assert!(
+      chatbot
+        .add_text_channel(ChannelId(2), Some(GuildId(2)))
+        .await?
+    );
+    assert!(
+      chatbot
+        .set_conversation_mode(ChannelId(2), conversation::Mode::Channel)
+        .await?
+    );
+
+    let mut cache = MockCache::new();
+    let value = stored.lock().unwrap().clone();
+    cache.expect_get().returning(move |_| Ok(value.clone()));
+    cache.expect_put().times(1).returning(|_, _, _| Ok(()));
+
+    let restarted = new_chatbot(cache);
+    restarted.restore_text_channels().await?;
+
+    assert_eq!(
+      vec![ChannelId(1)],
+      restarted.text_channels(Some(GuildId(1))).await
+    );
+    assert_eq!(
+      vec![ChannelId(2)],
+      restarted.text_channels(Some(GuildId(2))).await
+    );
+    assert_eq!(
+      Some(conversation::Mode::Channel),
+      restarted.conversation_mode(ChannelId(2)).await
+    );
+
+    assert!(restarted.remove_text_channel(ChannelId(1)).await?);
+    assert!(!restarted.remove_text_channel(ChannelId(1)).await?);
+    assert!(restarted.text_channels(Some(GuildId(1))).await.is_empty());
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn forgetting_deletes_the_conversation_stored_as_text() -> Result<()> {
+    let values: Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>> = Arc::default();
+    let user_id = UserId(1);
+    let legacy_key = user_id.0.to_le_bytes().to_vec();
+    values
+      .lock()
+      .unwrap()
+      .insert(legacy_key.clone(), b"Me: hi\nEliza: hello\n".to_vec());
+
+    let chatbot = new_chatbot(cache::in_memory(Arc::clone(&values)));
+    let key = ConversationKey::User(user_id);
+    assert_eq!(2, chatbot.conversation(key).await?.turns.len());
+
+    chatbot.forget_history(key).await?;
+
+    assert!(values.lock().unwrap().is_empty());
+    assert!(chatbot.conversation(key).await?.turns.is_empty());
+
+    // The conversation starts over from the persona context.
+    values
+      .lock()
+      .unwrap()
+      .insert(legacy_key.clone(), b"Me: hi\nEliza: hello\n".to_vec());
+    chatbot.reset_history(key).await?;
+
+    assert_eq!(None, values.lock().unwrap().get(&legacy_key));
+    assert!(values.lock().unwrap().contains_key(&key.cache_key()));
+    assert!(chatbot.conversation(key).await?.turns.is_empty());
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn text_channels_are_not_changed_when_they_cannot_be_persisted() -> Result<()> {
+    let mut cache = MockCache::new();
+    cache
+      .expect_put()
+      .returning(|_, _, _| Err(anyhow::anyhow!("redis is down")));
+
+    let chatbot = new_chatbot(cache);
+
+    assert!(chatbot
+      .add_text_channel(ChannelId(1), Some(GuildId(1)))
+      .await
+      .is_err());
+    assert!(chatbot.text_channels(Some(GuildId(1))).await.is_empty());
+
+    Ok(())
+  }
+
+  #[test]
+  fn users_cannot_be_labelled_like_the_bot() {
+    let tests = vec![
+      ("Ana", "Ana"),
+      ("Ana: hi\nEliza", "Ana  hi Eliza"),
+      ("  ", "User"),
+      ("eliza", "eliza (user)"),
+      ("Me", "Me (user)"),
+      ("Summary", "Summary (user)"),
+    ];
+
+    for (name, expected) in tests {
+      assert_eq!(expected, speaker_label(name, "Eliza"), "name={:?}", name);
+    }
+  }
+
+  #[test]
+  fn answers_in_the_guild_languages() {
+    let translations = vec![
+      (String::from("en"), String::from("hello")),
+      (String::from("pt"), String::from("olá")),
+    ];
+
+    let languages = answer_languages(&[], "pt");
+    assert_eq!(vec!["en", "pt"], languages);
+    assert_eq!(
+      "EN: hello\n\nPT: olá",
+      format_answer(&languages, &translations)
+    );
+
+    let languages = answer_languages(&[], "en");
+    assert_eq!(vec!["en"], languages);
+    assert_eq!("hello", format_answer(&languages, &translations));
+
+    let languages = answer_languages(&[String::from("user"), String::from("pt")], "pt");
+    assert_eq!(vec!["pt"], languages);
+  }
 
   #[test]
   fn test_remove_links_from_text() {
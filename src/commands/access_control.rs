@@ -0,0 +1,226 @@
+use std::{collections::HashSet, fmt::Display, sync::Arc};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serenity::{
+  client::Context,
+  model::{
+    guild::Member,
+    id::{RoleId, UserId},
+  },
+};
+
+use super::{Args, Command, Invocation};
+use crate::guild_settings::{GuildSettings, GuildSettingsStore};
+
+/// The permission level a user must have to invoke a command. Each level includes the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionLevel {
+  Everyone,
+  /// Users with one of the guild DJ roles.
+  Dj,
+  /// Users that can manage the guild or that have one of the guild admin roles.
+  Admin,
+  /// The users that own the bot.
+  Owner,
+}
+
+impl Display for PermissionLevel {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let name = match self {
+      PermissionLevel::Everyone => "everyone",
+      PermissionLevel::Dj => "DJ",
+      PermissionLevel::Admin => "admin",
+      PermissionLevel::Owner => "bot owner",
+    };
+
+    write!(f, "{}", name)
+  }
+}
+
+/// Decides if a user is allowed to invoke a command.
+pub struct AccessControl {
+  settings: Arc<GuildSettingsStore>,
+  /// The users that own the bot.
+  owners: HashSet<UserId>,
+}
+
+impl AccessControl {
+  pub fn new(settings: Arc<GuildSettingsStore>, owners: HashSet<UserId>) -> Self {
+    Self { settings, owners }
+  }
+
+  /// Returns the highest permission level of the user that invoked the command.
+  #[tracing::instrument(name = "AccessControl::level", skip_all)]
+  pub async fn level(&self, ctx: &Context, invocation: &Invocation) -> Result<PermissionLevel> {
+    let is_owner = self.owners.contains(&invocation.author().id);
+
+    let (settings, roles, is_guild_admin) = match invocation.guild_id() {
+      None => (GuildSettings::default(), vec![], false),
+      Some(guild_id) => {
+        let settings = self.settings.get(guild_id).await?;
+        match invocation.member(ctx).await? {
+          None => (settings, vec![], false),
+          Some(member) => {
+            let is_guild_admin = is_guild_admin(ctx, &member)?;
+            (settings, member.roles, is_guild_admin)
+          }
+        }
+      }
+    };
+
+    Ok(level(is_owner, is_guild_admin, &roles, &settings))
+  }
+
+  /// Returns Ok(()) if the user can invoke the command with `args`
+  /// or the permission level the user is missing.
+  pub async fn check(
+    &self,
+    ctx: &Context,
+    invocation: &Invocation,
+    command: &dyn Command,
+    args: &Args,
+  ) -> Result<Result<(), PermissionLevel>> {
+    let required = required_level(command, args);
+
+    if required == PermissionLevel::Everyone {
+      return Ok(Ok(()));
+    }
+
+    if self.level(ctx, invocation).await? >= required {
+      Ok(Ok(()))
+    } else {
+      Ok(Err(required))
+    }
+  }
+}
+
+/// The level required to invoke the command, subcommands may require a higher level than the command.
+fn required_level(command: &dyn Command, args: &Args) -> PermissionLevel {
+  let subcommand_level = match args.peek() {
+    // Commands execute their first subcommand when no subcommand is passed.
+    None => command.subcommands().first(),
+    Some(name) => command
+      .subcommands()
+      .iter()
+      .find(|subcommand| subcommand.name == name),
+  }
+  .map(|subcommand| subcommand.permission);
+
+  command
+    .permission()
+    .max(subcommand_level.unwrap_or(PermissionLevel::Everyone))
+}
+
+/// Returns true when the member can manage the guild.
+fn is_guild_admin(ctx: &Context, member: &Member) -> Result<bool> {
+  let permissions = member.permissions(ctx)?;
+  Ok(permissions.administrator() || permissions.manage_guild())
+}
+
+fn level(
+  is_owner: bool,
+  is_guild_admin: bool,
+  roles: &[RoleId],
+  settings: &GuildSettings,
+) -> PermissionLevel {
+  let has_any_role = |role_ids: &[RoleId]| roles.iter().any(|role| role_ids.contains(role));
+
+  if is_owner {
+    PermissionLevel::Owner
+  } else if is_guild_admin || has_any_role(&settings.admin_roles) {
+    PermissionLevel::Admin
+  } else if has_any_role(&settings.dj_roles) {
+    PermissionLevel::Dj
+  } else {
+    PermissionLevel::Everyone
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    commands::{EchoCommand, PrefixCommand},
+    contracts::cache::MockCache,
+  };
+
+  #[test]
+  fn test_level() {
+    let settings = GuildSettings {
+      dj_roles: vec![RoleId(1)],
+      admin_roles: vec![RoleId(2)],
+      ..Default::default()
+    };
+
+    let tests = vec![
+      (true, false, vec![], &settings, PermissionLevel::Owner),
+      (false, true, vec![], &settings, PermissionLevel::Admin),
+      (
+        false,
+        false,
+        vec![RoleId(2)],
+        &settings,
+        PermissionLevel::Admin,
+      ),
+      (
+        false,
+        false,
+        vec![RoleId(1)],
+        &settings,
+        PermissionLevel::Dj,
+      ),
+      (
+        false,
+        false,
+        vec![RoleId(3)],
+        &settings,
+        PermissionLevel::Everyone,
+      ),
+      (false, false, vec![], &settings, PermissionLevel::Everyone),
+    ];
+
+    for (is_owner, is_guild_admin, roles, settings, expected) in tests {
+      assert_eq!(
+        expected,
+        level(is_owner, is_guild_admin, &roles, settings),
+        "is_owner={} is_guild_admin={} roles={:?}",
+        is_owner,
+        is_guild_admin,
+        roles
+      );
+    }
+
+    // Nobody is a DJ until the guild chooses its DJ roles.
+    assert_eq!(
+      PermissionLevel::Everyone,
+      level(false, false, &[RoleId(1)], &GuildSettings::default())
+    );
+  }
+
+  #[test]
+  fn subcommands_can_require_a_higher_level() {
+    let prefix = PrefixCommand::new(Arc::new(GuildSettingsStore::new(
+      Arc::new(MockCache::new()),
+    )));
+
+    let tests: Vec<(&dyn Command, &str, PermissionLevel)> = vec![
+      (&EchoCommand, "hello", PermissionLevel::Everyone),
+      (&prefix, "", PermissionLevel::Everyone),
+      (&prefix, "show", PermissionLevel::Everyone),
+      (&prefix, "set !", PermissionLevel::Admin),
+      (&prefix, "reset", PermissionLevel::Admin),
+    ];
+
+    for (command, args, expected) in tests {
+      assert_eq!(
+        expected,
+        required_level(command, &Args::parse(args)),
+        "command={} args={}",
+        command.name(),
+        args
+      );
+    }
+  }
+}
@@ -0,0 +1,473 @@
+use std::{collections::VecDeque, time::Duration};
+
+use reqwest::Url;
+use serenity::model::id::{ChannelId, RoleId, UserId};
+
+/// The arguments passed to a command, without the command name.
+///
+/// Arguments are separated by whitespace unless they are quoted: `"my file.mp3"`.
+/// Flags like `--loop` and `--volume=50` can appear anywhere and are only
+/// parsed when the command asks for them.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Args {
+  tokens: VecDeque<Token>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Token {
+  /// The argument with the quotes removed.
+  value: String,
+  /// The argument as it was typed.
+  raw: String,
+  /// Arguments that start with a quote are never flags.
+  quoted: bool,
+}
+
+/// Errors caused by invalid arguments. The command usage is sent to the user when a command fails with one of them.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ArgError {
+  #[error("missing argument `{0}`")]
+  Missing(&'static str),
+  #[error("`{value}` is not a valid {kind} for `{name}`")]
+  Invalid {
+    name: &'static str,
+    kind: &'static str,
+    value: String,
+  },
+  #[error("unknown subcommand `{0}`")]
+  UnknownSubcommand(String),
+}
+
+/// A value that can be parsed from a command argument.
+pub trait FromArg: Sized {
+  /// What the value is, used in error messages.
+  const KIND: &'static str;
+
+  fn from_arg(arg: &str) -> Option<Self>;
+}
+
+impl Args {
+  /// Splits `input` at whitespaces that are not inside quotes.
+  pub fn parse(input: &str) -> Self {
+    Self {
+      tokens: tokenize(input).into(),
+    }
+  }
+
+  /// Returns the next argument without consuming it.
+  pub fn peek(&self) -> Option<&str> {
+    self.tokens.front().map(|token| token.value.as_str())
+  }
+
+  /// Consumes the remaining arguments and joins them with spaces, quotes are kept as they were typed.
+  pub fn rest(&mut self) -> String {
+    self
+      .tokens
+      .drain(..)
+      .map(|token| token.raw)
+      .collect::<Vec<_>>()
+      .join(" ")
+  }
+
+  /// Consumes the next argument, fails if there's no argument or if it is not a valid `T`.
+  pub fn required<T: FromArg>(&mut self, name: &'static str) -> Result<T, ArgError> {
+    self.optional(name)?.ok_or(ArgError::Missing(name))
+  }
+
+  /// Consumes the next argument if there's one, fails if it is not a valid `T`.
+  pub fn optional<T: FromArg>(&mut self, name: &'static str) -> Result<Option<T>, ArgError> {
+    self.next().map(|value| parse_arg(name, value)).transpose()
+  }
+
+  /// Consumes `--name` and returns true if it was passed.
+  pub fn flag(&mut self, name: &str) -> bool {
+    let flag = format!("--{}", name);
+    self.take_token(|token| token.value == flag).is_some()
+  }
+
+  /// Consumes `--name=value` and returns the value if the flag was passed.
+  pub fn flag_value<T: FromArg>(&mut self, name: &'static str) -> Result<Option<T>, ArgError> {
+    let flag = format!("--{}=", name);
+    self
+      .take_token(|token| token.value.starts_with(&flag))
+      .map(|token| parse_arg(name, token.value[flag.len()..].to_owned()))
+      .transpose()
+  }
+
+  /// Adds a flag as if it had been typed after the prefix, like `--loop` or `--volume=50`.
+  pub fn push_flag(&mut self, flag: String) {
+    self.tokens.push_back(Token {
+      raw: flag.clone(),
+      value: flag,
+      quoted: false,
+    });
+  }
+
+  /// Removes the first unquoted token that matches the predicate.
+  fn take_token(&mut self, predicate: impl Fn(&Token) -> bool) -> Option<Token> {
+    let index = self
+      .tokens
+      .iter()
+      .position(|token| !token.quoted && predicate(token))?;
+    self.tokens.remove(index)
+  }
+}
+
+fn parse_arg<T: FromArg>(name: &'static str, value: String) -> Result<T, ArgError> {
+  T::from_arg(&value).ok_or(ArgError::Invalid {
+    name,
+    kind: T::KIND,
+    value,
+  })
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+  let mut tokens = vec![];
+  let mut chars = input.chars().peekable();
+
+  loop {
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+    if chars.peek().is_none() {
+      return tokens;
+    }
+
+    let mut token = Token {
+      value: String::new(),
+      raw: String::new(),
+      quoted: false,
+    };
+    let mut quote = None;
+
+    while let Some(c) = chars.next_if(|c| quote.is_some() || !c.is_whitespace()) {
+      token.raw.push(c);
+
+      match (quote, c) {
+        // Only double quotes are quotes, single quotes are apostrophes like in `don't`.
+        (None, '"') => {
+          quote = Some(c);
+          if token.raw.len() == 1 {
+            token.quoted = true;
+          }
+        }
+        (Some(q), c) if c == q => quote = None,
+        (Some(_), '\\') => {
+          if let Some(escaped) = chars.next() {
+            token.raw.push(escaped);
+            token.value.push(escaped);
+          }
+        }
+        _ => token.value.push(c),
+      }
+    }
+
+    tokens.push(token);
+  }
+}
+
+impl From<Vec<String>> for Args {
+  fn from(values: Vec<String>) -> Self {
+    Self {
+      tokens: values
+        .into_iter()
+        .map(|value| Token {
+          raw: value.clone(),
+          value,
+          // Values from slash commands are never flags.
+          quoted: true,
+        })
+        .collect(),
+    }
+  }
+}
+
+impl Iterator for Args {
+  type Item = String;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.tokens.pop_front().map(|token| token.value)
+  }
+}
+
+impl FromArg for String {
+  const KIND: &'static str = "text";
+
+  fn from_arg(arg: &str) -> Option<Self> {
+    Some(arg.to_owned())
+  }
+}
+
+macro_rules! impl_from_arg_for_integer {
+  ($($t:ty),*) => {
+    $(
+      impl FromArg for $t {
+        const KIND: &'static str = "number";
+
+        fn from_arg(arg: &str) -> Option<Self> {
+          arg.parse().ok()
+        }
+      }
+    )*
+  };
+}
+
+impl_from_arg_for_integer!(u8, u16, u32, u64, i64, usize);
+
+impl FromArg for f32 {
+  const KIND: &'static str = "number";
+
+  fn from_arg(arg: &str) -> Option<Self> {
+    arg.parse().ok().filter(|value: &f32| value.is_finite())
+  }
+}
+
+impl FromArg for Url {
+  const KIND: &'static str = "url";
+
+  fn from_arg(arg: &str) -> Option<Self> {
+    // Discord hides link previews when links are wrapped in `<>`.
+    let arg = arg
+      .strip_prefix('<')
+      .and_then(|arg| arg.strip_suffix('>'))
+      .unwrap_or(arg);
+
+    Url::parse(arg)
+      .ok()
+      .filter(|url| matches!(url.scheme(), "http" | "https") && url.host().is_some())
+  }
+}
+
+impl FromArg for Duration {
+  const KIND: &'static str = "duration";
+
+  /// Accepts seconds (`90`), units (`1h2m30s`) and clock times (`1:02:30`).
+  /// Durations too long to count in seconds are invalid.
+  fn from_arg(arg: &str) -> Option<Self> {
+    if arg.contains(':') {
+      let mut seconds: u64 = 0;
+      for part in arg.split(':') {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+          return None;
+        }
+        seconds = seconds
+          .checked_mul(60)?
+          .checked_add(part.parse::<u64>().ok()?)?;
+      }
+      return Some(Duration::from_secs(seconds));
+    }
+
+    if let Ok(seconds) = arg.parse::<u64>() {
+      return Some(Duration::from_secs(seconds));
+    }
+
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in arg.chars() {
+      if c.is_ascii_digit() {
+        number.push(c);
+        continue;
+      }
+
+      let unit = match c {
+        'h' => 60 * 60,
+        'm' => 60,
+        's' => 1,
+        _ => return None,
+      };
+      seconds = number
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(unit)
+        .and_then(|part| seconds.checked_add(part))?;
+      number.clear();
+    }
+
+    if !number.is_empty() || arg.is_empty() {
+      return None;
+    }
+
+    Some(Duration::from_secs(seconds))
+  }
+}
+
+/// Parses a mention like `<@123>` or `<@!123>` using one of the prefixes, or a plain id like `123`.
+fn parse_mention(arg: &str, prefixes: &[&str]) -> Option<u64> {
+  let id = match arg.strip_prefix('<').and_then(|arg| arg.strip_suffix('>')) {
+    None => arg,
+    Some(mention) => prefixes
+      .iter()
+      .find_map(|prefix| mention.strip_prefix(prefix))?,
+  };
+
+  if !id.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+
+  id.parse().ok()
+}
+
+impl FromArg for UserId {
+  const KIND: &'static str = "user mention";
+
+  fn from_arg(arg: &str) -> Option<Self> {
+    parse_mention(arg, &["@!", "@"]).map(UserId)
+  }
+}
+
+impl FromArg for RoleId {
+  const KIND: &'static str = "role mention";
+
+  fn from_arg(arg: &str) -> Option<Self> {
+    parse_mention(arg, &["@&"]).map(RoleId)
+  }
+}
+
+impl FromArg for ChannelId {
+  const KIND: &'static str = "channel mention";
+
+  fn from_arg(arg: &str) -> Option<Self> {
+    parse_mention(arg, &["#"]).map(ChannelId)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse() {
+    let tests = vec![
+      ("", vec![]),
+      ("  a   b ", vec!["a", "b"]),
+      (
+        r#"playlocal "my file.mp3""#,
+        vec!["playlocal", "my file.mp3"],
+      ),
+      ("don't stop 'me' now", vec!["don't", "stop", "'me'", "now"]),
+      (r#"--name="a b" c"#, vec!["--name=a b", "c"]),
+      (r#""escaped \" quote""#, vec![r#"escaped " quote"#]),
+      (r#""unterminated quote"#, vec!["unterminated quote"]),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(
+        expected,
+        Args::parse(input).collect::<Vec<_>>(),
+        "input={}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn rest_keeps_the_arguments_as_typed() {
+    let mut args = Args::parse(r#"sethistory Me: "hi"  there"#);
+    args.next();
+    assert_eq!(r#"Me: "hi" there"#, args.rest());
+  }
+
+  #[test]
+  fn test_flags() {
+    let mut args = Args::parse(r#"https://a.com --loop --volume=50 "--quoted""#);
+
+    assert!(args.flag("loop"));
+    assert!(!args.flag("loop"));
+    assert_eq!(Ok(Some(50)), args.flag_value::<u8>("volume"));
+    assert_eq!(Ok(None), args.flag_value::<u8>("speed"));
+    assert!(!args.flag("quoted"));
+    assert_eq!(vec!["https://a.com", "--quoted"], args.collect::<Vec<_>>());
+
+    assert_eq!(
+      Err(ArgError::Invalid {
+        name: "volume",
+        kind: "number",
+        value: "loud".to_owned()
+      }),
+      Args::parse("--volume=loud").flag_value::<u8>("volume")
+    );
+
+    // Slash command values are never flags.
+    assert!(!Args::from(vec!["--loop".to_owned()]).flag("loop"));
+  }
+
+  #[test]
+  fn test_required_and_optional() {
+    let mut args = Args::parse("abc 10");
+
+    assert_eq!(
+      Err(ArgError::Invalid {
+        name: "url",
+        kind: "url",
+        value: "abc".to_owned()
+      }),
+      args.required::<Url>("url")
+    );
+    assert_eq!(Ok(10), args.required::<u32>("count"));
+    assert_eq!(Ok(None), args.optional::<u32>("count"));
+    assert_eq!(
+      Err(ArgError::Missing("count")),
+      args.required::<u32>("count")
+    );
+  }
+
+  #[test]
+  fn test_url() {
+    let tests = vec![
+      ("https://youtube.com/watch?v=1", true),
+      ("<https://youtube.com/watch?v=1>", true),
+      ("http://localhost:8080", true),
+      ("youtube.com", false),
+      ("file:///etc/passwd", false),
+      ("garbage", false),
+    ];
+
+    for (input, valid) in tests {
+      assert_eq!(valid, Url::from_arg(input).is_some(), "input={}", input);
+    }
+  }
+
+  #[test]
+  fn test_duration() {
+    let tests = vec![
+      ("90", Some(90)),
+      ("1:30", Some(90)),
+      ("1:02:03", Some(3723)),
+      ("1h2m3s", Some(3723)),
+      ("2m", Some(120)),
+      ("10", Some(10)),
+      ("", None),
+      ("1:", None),
+      ("5x", None),
+      ("m", None),
+      ("12m5", None),
+      ("99999999999999999999:0", None),
+      ("999999999999999999:0", None),
+      ("9999999999999999999h", None),
+      ("18446744073709551615s1s", None),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(
+        expected.map(Duration::from_secs),
+        Duration::from_arg(input),
+        "input={}",
+        input
+      );
+    }
+  }
+
+  #[test]
+  fn test_mentions() {
+    assert_eq!(Some(UserId(1)), UserId::from_arg("<@1>"));
+    assert_eq!(Some(UserId(1)), UserId::from_arg("<@!1>"));
+    assert_eq!(Some(UserId(1)), UserId::from_arg("1"));
+    assert_eq!(None, UserId::from_arg("<@&1>"));
+    assert_eq!(Some(RoleId(1)), RoleId::from_arg("<@&1>"));
+    assert_eq!(Some(RoleId(1)), RoleId::from_arg("1"));
+    assert_eq!(None, RoleId::from_arg("<@1>"));
+    assert_eq!(None, RoleId::from_arg("<@&abc>"));
+    assert_eq!(None, RoleId::from_arg(""));
+    assert_eq!(Some(ChannelId(1)), ChannelId::from_arg("<#1>"));
+    assert_eq!(None, ChannelId::from_arg("<@1>"));
+  }
+}
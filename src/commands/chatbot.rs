@@ -0,0 +1,649 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{
+  AccessControl, ArgError, Args, Command, CommandOption, Invocation, PermissionLevel, Subcommand,
+};
+use crate::{
+  chatbot::{ChatBot, ConversationKey, USER_LANGUAGE},
+  conversation::{self, Conversation},
+  language,
+  persona::{Persona, PersonaError},
+  text_generation::Parameter,
+};
+
+pub struct ChatBotCommand {
+  chatbot: Arc<ChatBot>,
+  access_control: Arc<AccessControl>,
+}
+
+impl ChatBotCommand {
+  pub fn new(chatbot: Arc<ChatBot>, access_control: Arc<AccessControl>) -> Self {
+    Self {
+      chatbot,
+      access_control,
+    }
+  }
+
+  /// Channel conversations are shared by everyone in the channel so only DJs can reset or export them.
+  /// Returns false after telling the user when the user cannot.
+  async fn check_conversation_access(
+    &self,
+    ctx: &Context,
+    invocation: &Invocation,
+    key: ConversationKey,
+  ) -> Result<bool> {
+    if let ConversationKey::User(_) = key {
+      return Ok(true);
+    }
+
+    if self.access_control.level(ctx, invocation).await? >= PermissionLevel::Dj {
+      return Ok(true);
+    }
+
+    invocation
+      .reply(
+        ctx,
+        format!(
+          "the channel conversation is shared, you need the {} permission level to change it",
+          PermissionLevel::Dj
+        ),
+      )
+      .await?;
+
+    Ok(false)
+  }
+}
+
+#[async_trait]
+impl Command for ChatBotCommand {
+  fn name(&self) -> &'static str {
+    "chatbot"
+  }
+
+  fn description(&self) -> &'static str {
+    "Makes the chatbot reply to messages sent in the channel"
+  }
+
+  fn usage(&self) -> &'static str {
+    "chatbot [join|leave|channels|mode [user|channel]|status|set <parameter> <value|default>|languages [<language>...|default]|personas|persona <name>|newpersona <name> <context>|editpersona <name> <field> <value|default>|delpersona <name>|eliza|forget|sethistory|history [page|export]|voice <enable|disable>]"
+  }
+
+  fn subcommands(&self) -> &'static [Subcommand] {
+    const SUBCOMMANDS: &[Subcommand] = &[
+      Subcommand {
+        name: "join",
+        usage: "chatbot join",
+        description: "Makes the chatbot reply to messages sent in the channel",
+        options: &[],
+        permission: PermissionLevel::Dj,
+      },
+      Subcommand {
+        name: "leave",
+        usage: "chatbot leave",
+        description: "Makes the chatbot stop replying to messages sent in the channel",
+        options: &[],
+        permission: PermissionLevel::Dj,
+      },
+      Subcommand {
+        name: "channels",
+        usage: "chatbot channels",
+        description: "Lists the channels the chatbot replies to messages in",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "mode",
+        usage: "chatbot mode [user|channel]",
+        description: "Shows or changes whether each user has their own conversation or the channel shares one",
+        options: &[CommandOption::new("mode", "user or channel")
+          .choices(&["user", "channel"])
+          .optional()],
+        permission: PermissionLevel::Dj,
+      },
+      Subcommand {
+        name: "status",
+        usage: "chatbot status",
+        description: "Shows the chatbot backend and sampling parameters used in the guild",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "set",
+        usage: "chatbot set <parameter> <value|default>",
+        description: "Changes a sampling parameter used in the guild",
+        options: &[
+          CommandOption::new("parameter", "The parameter to change").choices(&[
+            "temperature",
+            "repetition_penalty",
+            "top_p",
+            "top_k",
+            "max_tokens",
+          ]),
+          CommandOption::new("value", "The new value or default"),
+        ],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "languages",
+        usage: "chatbot languages [<language>...|default]",
+        description: "Shows or changes the languages the answers are shown in, user is the language of the message",
+        options: &[CommandOption::new("languages", "Language codes like en pt user, or default").optional()],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "personas",
+        usage: "chatbot personas",
+        description: "Lists the personas you can talk to",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "persona",
+        usage: "chatbot persona <name>",
+        description: "Talks to another persona, your conversation starts over",
+        options: &[CommandOption::new("name", "The persona name")],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "newpersona",
+        usage: "chatbot newpersona <name> <context>",
+        description: "Creates a persona",
+        options: &[
+          CommandOption::new("name", "The persona name"),
+          CommandOption::new("context", "The conversation users start with"),
+        ],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "editpersona",
+        usage: "chatbot editpersona <name> <field> <value|default>",
+        description: "Changes the context, voice, language or a sampling parameter of a persona",
+        options: &[
+          CommandOption::new("name", "The persona name"),
+          CommandOption::new("field", "context, voice, language or a sampling parameter"),
+          CommandOption::new("value", "The new value or default"),
+        ],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "delpersona",
+        usage: "chatbot delpersona <name>",
+        description: "Deletes a persona created with newpersona",
+        options: &[CommandOption::new("name", "The persona name")],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "eliza",
+        usage: "chatbot eliza",
+        description: "Resets your conversation with the chatbot, or the channel conversation",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "forget",
+        usage: "chatbot forget",
+        description: "Deletes your conversation with the chatbot, or the channel conversation",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "sethistory",
+        usage: "chatbot sethistory <history>",
+        description: "Replaces your conversation with the chatbot",
+        options: &[CommandOption::new("history", "The new conversation")],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "history",
+        usage: "chatbot history [page|export]",
+        description: "Shows your conversation with the chatbot, or the channel conversation, newest messages first",
+        options: &[
+          CommandOption::new("page", "The page number or export to get a JSON file").optional(),
+        ],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "voice",
+        usage: "chatbot voice <enable|disable>",
+        description: "Enables or disables the chatbot replies in the voice channel",
+        options: &[CommandOption::new("state", "Enable or disable").choices(&["enable", "disable"])],
+        permission: PermissionLevel::Dj,
+      },
+    ];
+
+    SUBCOMMANDS
+  }
+
+  #[tracing::instrument(name = "chatbot", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    let user_id = invocation.author().id;
+    let key = self
+      .chatbot
+      .conversation_key(invocation.channel_id(), user_id)
+      .await;
+
+    match args.next().as_deref() {
+      None | Some("join") => self.chatbot.join_text_channel(ctx, invocation).await?,
+      Some(subcommand) => match subcommand {
+        "leave" => self.chatbot.leave_text_channel(ctx, invocation).await?,
+        "channels" => {
+          let channels = self.chatbot.text_channels(invocation.guild_id()).await;
+
+          let reply = if channels.is_empty() {
+            String::from("the chatbot has not joined any channel")
+          } else {
+            channels
+              .iter()
+              .map(|channel_id| format!("<#{}>", channel_id))
+              .collect::<Vec<_>>()
+              .join("\n")
+          };
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "mode" => {
+          let channel_id = invocation.channel_id();
+
+          let reply = match args.optional::<String>("mode")? {
+            None => match self.chatbot.conversation_mode(channel_id).await {
+              None => String::from("chatbot is not in the channel"),
+              Some(mode) => format!("the channel is in {} mode", mode),
+            },
+            Some(mode) => {
+              let mode: conversation::Mode = mode.parse().map_err(|_| ArgError::Invalid {
+                name: "mode",
+                kind: "mode (user or channel)",
+                value: mode,
+              })?;
+
+              if self.chatbot.set_conversation_mode(channel_id, mode).await? {
+                format!("the channel is now in {} mode", mode)
+              } else {
+                String::from("chatbot is not in the channel")
+              }
+            }
+          };
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "status" => {
+          let status = self
+            .chatbot
+            .status(invocation.guild_id(), invocation.channel_id(), user_id)
+            .await?;
+
+          let mut reply = format!(
+            "persona: {}\nmode: {}\nlanguages: {}\nbackend: {}\nvoice: {}\nchannels: {}\n",
+            status.persona,
+            status
+              .conversation_mode
+              .map(|mode| mode.to_string())
+              .unwrap_or_else(|| String::from("not in the channel")),
+            status.answer_languages.join(" "),
+            status.backend,
+            if status.voice_enabled {
+              "enabled"
+            } else {
+              "disabled"
+            },
+            status.text_channels.len()
+          );
+          reply.push_str(&format!(
+            "translation cache: {} hits, {} misses\n",
+            status.translation_cache.hits, status.translation_cache.misses
+          ));
+          for parameter in Parameter::ALL {
+            reply.push_str(&format!(
+              "{}: {}{}\n",
+              parameter,
+              status.profile.get(parameter),
+              if status.changed_parameters.contains(&parameter) {
+                " (changed in this guild)"
+              } else {
+                ""
+              }
+            ));
+          }
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "set" => {
+          let guild_id = match invocation.guild_id() {
+            None => {
+              invocation
+                .reply(ctx, "the chatbot settings can only be changed in a guild")
+                .await?;
+              return Ok(());
+            }
+            Some(guild_id) => guild_id,
+          };
+
+          let parameter = match args.required::<String>("parameter")?.parse::<Parameter>() {
+            Err(err) => {
+              invocation.reply(ctx, err.to_string()).await?;
+              return Ok(());
+            }
+            Ok(parameter) => parameter,
+          };
+
+          let value = if args.peek() == Some("default") {
+            None
+          } else {
+            let value: f32 = args.required("value")?;
+            if let Err(err) = parameter.validate(value) {
+              invocation.reply(ctx, err.to_string()).await?;
+              return Ok(());
+            }
+            Some(value)
+          };
+
+          self
+            .chatbot
+            .set_generation_parameter(guild_id, parameter, value)
+            .await?;
+
+          let persona = self.chatbot.persona(invocation.author().id).await?;
+          let profile = self
+            .chatbot
+            .generation_profile(Some(guild_id), &persona)
+            .await?;
+          invocation
+            .reply(
+              ctx,
+              format!("{} is now {}", parameter, profile.get(parameter)),
+            )
+            .await?;
+        }
+        "languages" => {
+          let guild_id = match invocation.guild_id() {
+            None => {
+              invocation
+                .reply(ctx, "the chatbot settings can only be changed in a guild")
+                .await?;
+              return Ok(());
+            }
+            Some(guild_id) => guild_id,
+          };
+
+          let rest = args.rest();
+          if rest.is_empty() {
+            let status = self
+              .chatbot
+              .status(Some(guild_id), invocation.channel_id(), user_id)
+              .await?;
+            invocation
+              .reply(
+                ctx,
+                format!(
+                  "answers are shown in: {}",
+                  status.answer_languages.join(" ")
+                ),
+              )
+              .await?;
+            return Ok(());
+          }
+
+          let languages: Vec<String> = if rest == "default" {
+            Vec::new()
+          } else {
+            rest.split_whitespace().map(str::to_lowercase).collect()
+          };
+
+          if let Some(invalid) = languages
+            .iter()
+            .find(|lang| *lang != USER_LANGUAGE && !language::is_valid_code(lang))
+          {
+            return Err(
+              ArgError::Invalid {
+                name: "languages",
+                kind: "language code like en or pt",
+                value: invalid.clone(),
+              }
+              .into(),
+            );
+          }
+
+          self
+            .chatbot
+            .set_answer_languages(guild_id, languages)
+            .await?;
+          invocation.reply(ctx, "answer languages changed").await?;
+        }
+        "personas" => {
+          let personas = self.chatbot.personas().await?;
+          let current = self.chatbot.persona(invocation.author().id).await?;
+
+          let reply = personas
+            .iter()
+            .map(|persona| {
+              if persona.name == current.name {
+                format!("{} (talking to you)", persona.name)
+              } else {
+                persona.name.clone()
+              }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "persona" => {
+          let name: String = args.required("name")?;
+
+          let reply = match self
+            .chatbot
+            .select_persona(invocation.author().id, &name)
+            .await
+          {
+            Ok(persona) => format!("you are now talking to {}", persona.name),
+            Err(err) => persona_error_reply(err)?,
+          };
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "newpersona" => {
+          let name: String = args.required("name")?;
+          let initial_context = args.rest();
+          if initial_context.is_empty() {
+            return Err(ArgError::Missing("context").into());
+          }
+
+          let persona = Persona {
+            name,
+            initial_context,
+            ..Default::default()
+          };
+
+          let reply = match self.chatbot.create_persona(persona.clone()).await {
+            Ok(()) => format!("persona {} created", persona.name),
+            Err(err) => persona_error_reply(err)?,
+          };
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "editpersona" => {
+          let name: String = args.required("name")?;
+          let field: String = args.required("field")?;
+          let value = args.rest();
+          let value = match value.as_str() {
+            "" => return Err(ArgError::Missing("value").into()),
+            "default" => None,
+            value => Some(value),
+          };
+
+          let reply = match self.chatbot.edit_persona(&name, &field, value).await {
+            Ok(persona) => format!("persona {} changed", persona.name),
+            Err(err) => persona_error_reply(err)?,
+          };
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "delpersona" => {
+          let name: String = args.required("name")?;
+
+          let reply = match self.chatbot.delete_persona(&name).await {
+            Ok(()) => format!("persona {} deleted", name),
+            Err(err) => persona_error_reply(err)?,
+          };
+
+          invocation.reply(ctx, reply).await?;
+        }
+        "eliza" => {
+          if !self.check_conversation_access(ctx, invocation, key).await? {
+            return Ok(());
+          }
+          self.chatbot.reset_history(key).await?;
+          invocation.reply(ctx, "history set").await?;
+        }
+        "forget" => {
+          if !self.check_conversation_access(ctx, invocation, key).await? {
+            return Ok(());
+          }
+          self.chatbot.forget_history(key).await?;
+          invocation.reply(ctx, "history deleted").await?;
+        }
+        "sethistory" => {
+          self.chatbot.set_user_history(user_id, &args.rest()).await?;
+          invocation.reply(ctx, "history set").await?;
+        }
+        "history" => {
+          let conversation = self.chatbot.conversation(key).await?;
+
+          if args.peek() == Some("export") {
+            if !self.check_conversation_access(ctx, invocation, key).await? {
+              return Ok(());
+            }
+            invocation
+              .reply_with_file(
+                ctx,
+                "your conversation with the chatbot",
+                "history.json",
+                serde_json::to_vec_pretty(&conversation)?,
+              )
+              .await?;
+            return Ok(());
+          }
+
+          let page: usize = args.optional("page")?.unwrap_or(1);
+          invocation
+            .reply(ctx, history_page(&conversation, page))
+            .await?;
+        }
+        "voice" => {
+          let state: String = args.required("state")?;
+          let guild_id = match invocation.guild_id() {
+            None => {
+              invocation
+                .reply(ctx, "voice chat can only be changed in a guild")
+                .await?;
+              return Ok(());
+            }
+            Some(guild_id) => guild_id,
+          };
+          match state.as_str() {
+            "enable" => {
+              self.chatbot.set_voice_enabled(guild_id, true).await?;
+              invocation.reply(ctx, "voice chat enabled").await?;
+            }
+            "disable" => {
+              self.chatbot.set_voice_enabled(guild_id, false).await?;
+              invocation.reply(ctx, "voice chat disabled").await?;
+            }
+            _ => {
+              return Err(
+                ArgError::Invalid {
+                  name: "state",
+                  kind: "state (enable or disable)",
+                  value: state,
+                }
+                .into(),
+              )
+            }
+          }
+        }
+        _ => return Err(ArgError::UnknownSubcommand(subcommand.to_owned()).into()),
+      },
+    }
+
+    Ok(())
+  }
+}
+
+/// Returns the message to reply with when the persona could not be changed because of
+/// something the user did, like using a name that does not exist.
+fn persona_error_reply(err: anyhow::Error) -> Result<String> {
+  match err.downcast::<PersonaError>() {
+    Ok(err) => Ok(err.to_string()),
+    Err(err) => Err(err),
+  }
+}
+
+/// How many turns are shown in each page of the history.
+const HISTORY_PAGE_SIZE: usize = 5;
+
+/// Turns longer than this are cut so a page fits in a discord message.
+const MAX_HISTORY_TURN_LEN: usize = 300;
+
+/// Returns a page of the conversation, the first page has the newest turns.
+fn history_page(conversation: &Conversation, page: usize) -> String {
+  if conversation.turns.is_empty() {
+    return String::from("you have not talked to the chatbot yet");
+  }
+
+  let pages = conversation.turns.len().div_ceil(HISTORY_PAGE_SIZE);
+  let page = page.clamp(1, pages);
+
+  let mut reply = format!("page {}/{}\n", page, pages);
+
+  for turn in conversation
+    .turns
+    .iter()
+    .rev()
+    .skip((page - 1) * HISTORY_PAGE_SIZE)
+    .take(HISTORY_PAGE_SIZE)
+  {
+    let mut text: String = turn.text.chars().take(MAX_HISTORY_TURN_LEN).collect();
+    if text.len() < turn.text.len() {
+      text.push('…');
+    }
+
+    reply.push_str(&format!(
+      "<t:{}:f> **{}**: {}\n",
+      turn.timestamp.unix_timestamp(),
+      conversation.speaker_name(turn),
+      text
+    ));
+  }
+
+  reply
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::conversation::{Speaker, Turn};
+
+  #[test]
+  fn history_pages_fit_in_a_message() {
+    let mut conversation = Conversation::new("Eliza: hi", "Eliza");
+    for i in 0..12 {
+      conversation.push(Turn::new(
+        Speaker::User,
+        &format!("{}{}", i, "ã".repeat(1000)),
+      ));
+    }
+
+    let first = history_page(&conversation, 1);
+    assert!(first.starts_with("page 1/3\n"));
+    assert!(first.contains("**Me**: 11"));
+    assert!(first.chars().count() <= 2000);
+
+    let last = history_page(&conversation, 10);
+    assert!(last.starts_with("page 3/3\n"));
+    assert!(last.contains("**Me**: 0"));
+  }
+}
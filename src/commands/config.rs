@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{ArgError, Args, Command, Invocation, PermissionLevel, Subcommand};
+use crate::config::ConfigReloader;
+
+/// Replies longer than this are sent as a file because they do not fit in a message.
+const MAX_REPLY_LENGTH: usize = 1900;
+
+pub struct ConfigCommand {
+  reloader: Arc<ConfigReloader>,
+}
+
+impl ConfigCommand {
+  pub fn new(reloader: Arc<ConfigReloader>) -> Self {
+    Self { reloader }
+  }
+}
+
+#[async_trait]
+impl Command for ConfigCommand {
+  fn name(&self) -> &'static str {
+    "config"
+  }
+
+  fn description(&self) -> &'static str {
+    "Manages the bot config"
+  }
+
+  fn usage(&self) -> &'static str {
+    "config reload"
+  }
+
+  fn permission(&self) -> PermissionLevel {
+    PermissionLevel::Owner
+  }
+
+  fn subcommands(&self) -> &'static [Subcommand] {
+    const SUBCOMMANDS: &[Subcommand] = &[Subcommand {
+      name: "reload",
+      usage: "config reload",
+      description: "Reads the config file again and applies the changes",
+      options: &[],
+      permission: PermissionLevel::Owner,
+    }];
+
+    SUBCOMMANDS
+  }
+
+  #[tracing::instrument(name = "config", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    let subcommand = args.next().unwrap_or_else(|| String::from("reload"));
+
+    if subcommand != "reload" {
+      return Err(ArgError::UnknownSubcommand(subcommand).into());
+    }
+
+    let reply = match self.reloader.reload().await {
+      Err(err) => format!("the config was not reloaded, {}", err),
+      Ok(changes) if changes.is_empty() => String::from("the config did not change"),
+      Ok(changes) => format!("config reloaded:\n{}", changes.join("\n")),
+    };
+
+    if reply.len() <= MAX_REPLY_LENGTH {
+      invocation.reply(ctx, reply).await?;
+    } else {
+      invocation
+        .reply_with_file(
+          ctx,
+          "the changes do not fit in a message",
+          "config.txt",
+          reply.into_bytes(),
+        )
+        .await?;
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{Args, Command, CommandOption, Invocation};
+
+pub struct EchoCommand;
+
+#[async_trait]
+impl Command for EchoCommand {
+  fn name(&self) -> &'static str {
+    "echo"
+  }
+
+  fn description(&self) -> &'static str {
+    "Replies with the text that was sent"
+  }
+
+  fn usage(&self) -> &'static str {
+    "echo <text>"
+  }
+
+  fn options(&self) -> &'static [CommandOption] {
+    const OPTIONS: &[CommandOption] = &[CommandOption::new("text", "The text to reply with")];
+
+    OPTIONS
+  }
+
+  #[tracing::instrument(name = "echo", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    invocation.reply(ctx, args.rest()).await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,213 @@
+use std::{fmt::Write, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{Args, Command, CommandOption, Invocation, Registry};
+use crate::guild_settings::GuildSettingsStore;
+
+/// Lists the commands in the registry it was created with.
+pub struct HelpCommand {
+  registry: Registry,
+  settings: Arc<GuildSettingsStore>,
+}
+
+impl HelpCommand {
+  /// `registry` should contain every other command because commands
+  /// registered after the help command is created won't be listed.
+  pub fn new(registry: Registry, settings: Arc<GuildSettingsStore>) -> Self {
+    Self { registry, settings }
+  }
+}
+
+#[async_trait]
+impl Command for HelpCommand {
+  fn name(&self) -> &'static str {
+    "help"
+  }
+
+  fn description(&self) -> &'static str {
+    "Lists the available commands"
+  }
+
+  fn usage(&self) -> &'static str {
+    "help [command]"
+  }
+
+  fn options(&self) -> &'static [CommandOption] {
+    const OPTIONS: &[CommandOption] =
+      &[CommandOption::new("command", "The command to see the details of").optional()];
+
+    OPTIONS
+  }
+
+  #[tracing::instrument(name = "help", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    let prefix = self.settings.prefix(invocation.guild_id()).await?;
+
+    let reply = match args.next() {
+      None => command_list(&self.registry, &prefix)?,
+      Some(name) => match self.registry.find(&name) {
+        Some(command) => command_details(command.as_ref(), &prefix)?,
+        None => match self.registry.suggest(&name) {
+          None => format!("unknown command `{}`", name),
+          Some(suggestion) => format!(
+            "unknown command `{}`. Did you mean `{}help {}`?",
+            name, prefix, suggestion
+          ),
+        },
+      },
+    };
+
+    invocation.reply(ctx, reply).await?;
+
+    Ok(())
+  }
+}
+
+fn command_list(registry: &Registry, prefix: &str) -> Result<String> {
+  let mut buffer = String::from("**Commands**\n");
+
+  for command in registry.commands() {
+    writeln!(
+      &mut buffer,
+      "`{}{}` - {}",
+      prefix,
+      command.usage(),
+      command.description()
+    )?;
+
+    for subcommand in command.subcommands() {
+      writeln!(
+        &mut buffer,
+        "  `{}{}` - {}",
+        prefix, subcommand.usage, subcommand.description
+      )?;
+    }
+  }
+
+  write!(
+    &mut buffer,
+    "\nUse `{}help <command>` to see the details of a command.",
+    prefix
+  )?;
+
+  Ok(buffer)
+}
+
+fn command_details(command: &dyn Command, prefix: &str) -> Result<String> {
+  let mut buffer = String::new();
+
+  writeln!(
+    &mut buffer,
+    "**{}** - {}",
+    command.name(),
+    command.description()
+  )?;
+  writeln!(&mut buffer, "Usage: `{}{}`", prefix, command.usage())?;
+
+  if !command.aliases().is_empty() {
+    writeln!(&mut buffer, "Aliases: {}", command.aliases().join(", "))?;
+  }
+
+  if !command.subcommands().is_empty() {
+    writeln!(&mut buffer, "Subcommands:")?;
+
+    for subcommand in command.subcommands() {
+      writeln!(
+        &mut buffer,
+        "  `{}{}` - {}",
+        prefix, subcommand.usage, subcommand.description
+      )?;
+    }
+  }
+
+  Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::Arc;
+
+  use super::*;
+  use crate::commands::{PermissionLevel, Subcommand};
+
+  struct VoiceCommand;
+
+  #[async_trait]
+  impl Command for VoiceCommand {
+    fn name(&self) -> &'static str {
+      "voice"
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+      &["v"]
+    }
+
+    fn description(&self) -> &'static str {
+      "Voice chat"
+    }
+
+    fn usage(&self) -> &'static str {
+      "voice <enable|disable>"
+    }
+
+    fn subcommands(&self) -> &'static [Subcommand] {
+      &[
+        Subcommand {
+          name: "enable",
+          usage: "voice enable",
+          description: "Enables voice chat",
+          options: &[],
+          permission: PermissionLevel::Everyone,
+        },
+        Subcommand {
+          name: "disable",
+          usage: "voice disable",
+          description: "Disables voice chat",
+          options: &[],
+          permission: PermissionLevel::Everyone,
+        },
+      ]
+    }
+
+    async fn execute(&self, _ctx: &Context, _invocation: &Invocation, _args: Args) -> Result<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn lists_commands_and_subcommands() -> Result<()> {
+    let mut registry = Registry::new();
+    registry.register(Arc::new(VoiceCommand))?;
+
+    assert_eq!(
+      "**Commands**
+`b!voice <enable|disable>` - Voice chat
+  `b!voice enable` - Enables voice chat
+  `b!voice disable` - Disables voice chat
+
+Use `b!help <command>` to see the details of a command.",
+      command_list(&registry, "b!")?
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn shows_command_details() -> Result<()> {
+    assert_eq!(
+      "**voice** - Voice chat
+Usage: `b!voice <enable|disable>`
+Aliases: v
+Subcommands:
+  `b!voice enable` - Enables voice chat
+  `b!voice disable` - Disables voice chat
+",
+      command_details(&VoiceCommand, "b!")?
+    );
+
+    Ok(())
+  }
+}
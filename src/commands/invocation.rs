@@ -0,0 +1,186 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::{
+  client::Context,
+  model::{
+    channel::{AttachmentType, Message},
+    guild::Member,
+    id::{ChannelId, GuildId},
+    user::User,
+  },
+};
+
+/// Where a command has been invoked from.
+#[derive(Clone)]
+enum Source {
+  /// A message that starts with the prefix. Example: `b!video <url>`.
+  Message(Message),
+  /// A slash command. Example: `/video <url>`.
+  Interaction(ApplicationCommandInteraction),
+}
+
+/// Commands use the invocation to reply and to know who invoked them
+/// without having to care about how they were invoked.
+pub struct Invocation {
+  source: Source,
+  /// Has a slash command interaction been responded to?
+  responded: AtomicBool,
+}
+
+impl Clone for Invocation {
+  fn clone(&self) -> Self {
+    Self {
+      source: self.source.clone(),
+      responded: AtomicBool::new(self.responded.load(Ordering::SeqCst)),
+    }
+  }
+}
+
+impl From<Message> for Invocation {
+  fn from(msg: Message) -> Self {
+    Self {
+      source: Source::Message(msg),
+      responded: AtomicBool::new(false),
+    }
+  }
+}
+
+impl From<ApplicationCommandInteraction> for Invocation {
+  fn from(interaction: ApplicationCommandInteraction) -> Self {
+    Self {
+      source: Source::Interaction(interaction),
+      responded: AtomicBool::new(false),
+    }
+  }
+}
+
+impl Invocation {
+  /// The user that invoked the command.
+  pub fn author(&self) -> &User {
+    match &self.source {
+      Source::Message(msg) => &msg.author,
+      Source::Interaction(interaction) => &interaction.user,
+    }
+  }
+
+  /// The guild the command was invoked in. It is None in direct messages.
+  pub fn guild_id(&self) -> Option<GuildId> {
+    match &self.source {
+      Source::Message(msg) => msg.guild_id,
+      Source::Interaction(interaction) => interaction.guild_id,
+    }
+  }
+
+  /// The text channel the command was invoked in.
+  pub fn channel_id(&self) -> ChannelId {
+    match &self.source {
+      Source::Message(msg) => msg.channel_id,
+      Source::Interaction(interaction) => interaction.channel_id,
+    }
+  }
+
+  /// The guild member that invoked the command. It is None in direct messages.
+  /// The member is read from the cache and only requested to discord when it is not cached.
+  pub async fn member(&self, ctx: &Context) -> Result<Option<Member>> {
+    match &self.source {
+      Source::Message(msg) => match msg.guild_id {
+        None => Ok(None),
+        Some(guild_id) => match ctx.cache.member(guild_id, msg.author.id) {
+          Some(member) => Ok(Some(member)),
+          None => Ok(Some(guild_id.member(ctx, msg.author.id).await?)),
+        },
+      },
+      Source::Interaction(interaction) => Ok(interaction.member.clone()),
+    }
+  }
+
+  #[tracing::instrument(name = "Invocation::reply", skip_all)]
+  pub async fn reply(&self, ctx: &Context, content: impl Into<String>) -> Result<()> {
+    let content: String = content.into();
+
+    match &self.source {
+      Source::Message(msg) => {
+        msg.reply(ctx, content).await?;
+      }
+      Source::Interaction(interaction) => {
+        // The first reply replaces the "thinking" message shown after the interaction is acknowledged.
+        if self.responded.swap(true, Ordering::SeqCst) {
+          interaction
+            .create_followup_message(&ctx.http, |message| message.content(content))
+            .await?;
+        } else {
+          interaction
+            .edit_original_interaction_response(&ctx.http, |response| response.content(content))
+            .await?;
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Replies with a file attached, like a JSON export.
+  #[tracing::instrument(name = "Invocation::reply_with_file", skip_all, fields(filename = %filename))]
+  pub async fn reply_with_file(
+    &self,
+    ctx: &Context,
+    content: impl Into<String>,
+    filename: &str,
+    data: Vec<u8>,
+  ) -> Result<()> {
+    let content: String = content.into();
+    let file = AttachmentType::Bytes {
+      data: data.into(),
+      filename: filename.to_owned(),
+    };
+
+    match &self.source {
+      Source::Message(msg) => {
+        msg
+          .channel_id
+          .send_message(ctx, |message| {
+            message
+              .content(content)
+              .add_file(file)
+              .reference_message(msg)
+          })
+          .await?;
+      }
+      Source::Interaction(interaction) => {
+        // The response to a deferred interaction cannot be edited to add files, a follow up is sent instead.
+        self.responded.store(true, Ordering::SeqCst);
+        interaction
+          .create_followup_message(&ctx.http, |message| message.content(content).add_file(file))
+          .await?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Lets discord know that the slash command is being handled.
+  /// Discord waits only 3 seconds for a response so this must be called before executing the command.
+  #[tracing::instrument(name = "Invocation::acknowledge", skip_all)]
+  pub async fn acknowledge(&self, ctx: &Context) -> Result<()> {
+    if let Source::Interaction(interaction) = &self.source {
+      interaction.defer(&ctx.http).await?;
+    }
+
+    Ok(())
+  }
+
+  /// Replies to slash commands that finished executing without replying,
+  /// otherwise discord would keep showing that the bot is thinking.
+  #[tracing::instrument(name = "Invocation::finish", skip_all)]
+  pub async fn finish(&self, ctx: &Context) -> Result<()> {
+    if let Source::Interaction(_) = &self.source {
+      if !self.responded.load(Ordering::SeqCst) {
+        self.reply(ctx, "done").await?;
+      }
+    }
+
+    Ok(())
+  }
+}
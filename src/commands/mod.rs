@@ -0,0 +1,503 @@
+//! Commands invoked by sending a message that starts with the bot prefix, e.g. `b!echo hello`,
+//! or by using the equivalent slash command, e.g. `/echo hello`.
+//!
+//! Every command lives in its own module and is added to the [Registry] at startup,
+//! the bot only looks commands up in the registry and has no knowledge of what they do.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::{client::Context, model::id::UserId};
+
+/// The prefix messages must start with to be handled as commands
+/// when the guild has not configured another prefix.
+pub const PREFIX: &str = "b!";
+
+mod access_control;
+mod args;
+mod chatbot;
+mod config;
+mod echo;
+mod help;
+mod invocation;
+mod permissions;
+mod prefix;
+mod say;
+pub mod slash;
+mod sound;
+mod tts;
+mod video;
+mod videoskip;
+mod zanders;
+
+pub use access_control::{AccessControl, PermissionLevel};
+pub use args::{ArgError, Args};
+pub use chatbot::ChatBotCommand;
+pub use config::ConfigCommand;
+pub use echo::EchoCommand;
+pub use help::HelpCommand;
+pub use invocation::Invocation;
+pub use permissions::PermissionsCommand;
+pub use prefix::PrefixCommand;
+pub use say::SayCommand;
+pub use sound::SoundCommand;
+pub use tts::TtsCommand;
+pub use video::VideoCommand;
+pub use videoskip::VideoSkipCommand;
+pub use zanders::ZandersCommand;
+
+#[async_trait]
+pub trait Command: Send + Sync {
+  /// The name used to invoke the command.
+  fn name(&self) -> &'static str;
+
+  /// Other names that can be used to invoke the command.
+  fn aliases(&self) -> &'static [&'static str] {
+    &[]
+  }
+
+  /// A short explanation of what the command does.
+  fn description(&self) -> &'static str;
+
+  /// How the command should be invoked, without the prefix. Example: `sound playlocal <file>`.
+  fn usage(&self) -> &'static str;
+
+  /// The permission level required to invoke the command.
+  fn permission(&self) -> PermissionLevel {
+    PermissionLevel::Everyone
+  }
+
+  /// The subcommands accepted by the command, used to build the help message and the slash command.
+  /// The first subcommand is the one executed when no subcommand is passed.
+  fn subcommands(&self) -> &'static [Subcommand] {
+    &[]
+  }
+
+  /// The options accepted by the slash command when the command has no subcommands.
+  fn options(&self) -> &'static [CommandOption] {
+    &[]
+  }
+
+  /// Returns the suggestions shown while the user is typing the value of an option
+  /// that has autocomplete enabled.
+  async fn autocomplete(&self, _option: &str, _value: &str) -> Result<Vec<String>> {
+    Ok(vec![])
+  }
+
+  /// Executes the command. `args` contains everything after the command name.
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, args: Args) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Subcommand {
+  pub name: &'static str,
+  /// How the subcommand should be invoked, without the prefix. Example: `chatbot voice <enable|disable>`.
+  pub usage: &'static str,
+  pub description: &'static str,
+  /// The options accepted by the slash command.
+  pub options: &'static [CommandOption],
+  /// The permission level required to invoke the subcommand,
+  /// it is ignored when it is lower than the command permission level.
+  pub permission: PermissionLevel,
+}
+
+/// The kind of value a slash command option accepts, discord validates it and
+/// shows a picker for roles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionKind {
+  String,
+  Integer,
+  Boolean,
+  Role,
+}
+
+/// A slash command option, options are passed to the command
+/// as arguments in the order they are declared.
+#[derive(Debug, Clone, Copy)]
+pub struct CommandOption {
+  pub name: &'static str,
+  pub description: &'static str,
+  pub kind: OptionKind,
+  pub required: bool,
+  /// When not empty, the option value must be one of the choices.
+  pub choices: &'static [&'static str],
+  /// Should [Command::autocomplete] be called while the user types the option value?
+  pub autocomplete: bool,
+  /// The option is passed as a flag, `--name` for booleans and `--name=value` otherwise.
+  pub flag: bool,
+  /// The minimum and maximum values of integer options.
+  pub range: Option<(i64, i64)>,
+}
+
+impl CommandOption {
+  /// Creates a required text option.
+  pub const fn new(name: &'static str, description: &'static str) -> Self {
+    Self {
+      name,
+      description,
+      kind: OptionKind::String,
+      required: true,
+      choices: &[],
+      autocomplete: false,
+      flag: false,
+      range: None,
+    }
+  }
+
+  pub const fn kind(self, kind: OptionKind) -> Self {
+    Self { kind, ..self }
+  }
+
+  /// Makes the option an optional flag.
+  pub const fn flag(self) -> Self {
+    Self {
+      flag: true,
+      required: false,
+      ..self
+    }
+  }
+
+  pub const fn range(self, min: i64, max: i64) -> Self {
+    Self {
+      range: Some((min, max)),
+      ..self
+    }
+  }
+
+  pub const fn optional(self) -> Self {
+    Self {
+      required: false,
+      ..self
+    }
+  }
+
+  pub const fn choices(self, choices: &'static [&'static str]) -> Self {
+    Self { choices, ..self }
+  }
+
+  pub const fn autocomplete(self) -> Self {
+    Self {
+      autocomplete: true,
+      ..self
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RegistryError {
+  #[error("a command named {0} is already registered")]
+  DuplicatedName(String),
+}
+
+/// The result of looking up the command in a message.
+pub enum Resolution<'a> {
+  /// The message contains only the prefix.
+  Empty,
+  /// There's no command registered with the name used in the message.
+  Unknown(&'a str),
+  Found {
+    command: Arc<dyn Command>,
+    args: Args,
+  },
+}
+
+#[derive(Default, Clone)]
+pub struct Registry {
+  /// Commands in the order they were registered.
+  commands: Vec<Arc<dyn Command>>,
+  /// Maps command names and aliases to the command index in `commands`.
+  names: HashMap<&'static str, usize>,
+}
+
+impl Registry {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds a command to the registry. Fails if the command name or one of its aliases
+  /// is already used by another command.
+  pub fn register(&mut self, command: Arc<dyn Command>) -> Result<&mut Self, RegistryError> {
+    let names: Vec<&'static str> = std::iter::once(command.name())
+      .chain(command.aliases().iter().copied())
+      .collect();
+
+    if let Some(name) = names.iter().find(|name| self.names.contains_key(*name)) {
+      return Err(RegistryError::DuplicatedName(name.to_string()));
+    }
+
+    let index = self.commands.len();
+    for name in names {
+      self.names.insert(name, index);
+    }
+    self.commands.push(command);
+
+    Ok(self)
+  }
+
+  /// Returns the command that can be invoked by `name`, `name` may be an alias.
+  pub fn find(&self, name: &str) -> Option<Arc<dyn Command>> {
+    self
+      .names
+      .get(name)
+      .map(|index| Arc::clone(&self.commands[*index]))
+  }
+
+  /// Returns every registered command in the order they were registered.
+  pub fn commands(&self) -> impl Iterator<Item = &Arc<dyn Command>> {
+    self.commands.iter()
+  }
+
+  /// Returns the command name or alias that's closest to `name` if there's one close enough
+  /// to assume it was a typo.
+  pub fn suggest(&self, name: &str) -> Option<&'static str> {
+    // Names with more edits than this are probably not typos.
+    const MAX_DISTANCE: usize = 2;
+
+    self
+      .names
+      .keys()
+      .map(|candidate| (levenshtein_distance(name, candidate), *candidate))
+      .filter(|(distance, _)| *distance <= MAX_DISTANCE)
+      .min()
+      .map(|(_, candidate)| candidate)
+  }
+
+  /// Finds the command invoked by `input`. `input` is the message content without the prefix.
+  pub fn resolve<'a>(&self, input: &'a str) -> Resolution<'a> {
+    let input = input.trim_start();
+    let (name, args) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+
+    if name.is_empty() {
+      return Resolution::Empty;
+    }
+
+    match self.find(name) {
+      None => Resolution::Unknown(name),
+      Some(command) => Resolution::Found {
+        command,
+        args: Args::parse(args),
+      },
+    }
+  }
+}
+
+/// Returns the usage of the subcommand named `subcommand` or the command usage if there's no such subcommand.
+pub fn usage(command: &dyn Command, subcommand: Option<&str>) -> &'static str {
+  command
+    .subcommands()
+    .iter()
+    .find(|candidate| Some(candidate.name) == subcommand)
+    .map(|subcommand| subcommand.usage)
+    .unwrap_or_else(|| command.usage())
+}
+
+/// Returns the message content without the prefix if the message is a command.
+/// Mentioning the bot works as a prefix in every guild. Example: `@bot help`.
+pub fn strip_prefix<'a>(content: &'a str, prefix: &str, bot_id: UserId) -> Option<&'a str> {
+  let mentions = [format!("<@{}>", bot_id), format!("<@!{}>", bot_id)];
+
+  mentions
+    .iter()
+    .find_map(|mention| content.strip_prefix(mention.as_str()))
+    .or_else(|| content.strip_prefix(prefix))
+}
+
+/// Returns the minimum number of single character insertions, deletions or
+/// substitutions needed to turn `a` into `b`.
+fn levenshtein_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+
+  // Distances between the prefix of `a` seen so far and every prefix of `b`.
+  let mut distances: Vec<usize> = (0..=b.len()).collect();
+
+  for (i, a_char) in a.chars().enumerate() {
+    let mut previous_diagonal = distances[0];
+    distances[0] = i + 1;
+
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution_cost = if a_char == *b_char { 0 } else { 1 };
+
+      let distance = (previous_diagonal + substitution_cost)
+        .min(distances[j] + 1)
+        .min(distances[j + 1] + 1);
+
+      previous_diagonal = distances[j + 1];
+      distances[j + 1] = distance;
+    }
+  }
+
+  distances[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  struct FakeCommand {
+    name: &'static str,
+    aliases: &'static [&'static str],
+  }
+
+  #[async_trait]
+  impl Command for FakeCommand {
+    fn name(&self) -> &'static str {
+      self.name
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+      self.aliases
+    }
+
+    fn description(&self) -> &'static str {
+      "fake command"
+    }
+
+    fn usage(&self) -> &'static str {
+      self.name
+    }
+
+    async fn execute(&self, _ctx: &Context, _invocation: &Invocation, _args: Args) -> Result<()> {
+      Ok(())
+    }
+  }
+
+  fn registry() -> Registry {
+    let mut registry = Registry::new();
+    registry
+      .register(Arc::new(FakeCommand {
+        name: "sound",
+        aliases: &["s"],
+      }))
+      .unwrap()
+      .register(Arc::new(FakeCommand {
+        name: "video",
+        aliases: &[],
+      }))
+      .unwrap();
+    registry
+  }
+
+  #[test]
+  fn resolves_command_by_name_and_alias() {
+    let registry = registry();
+
+    for input in ["sound playlocal a.mp3", "s playlocal a.mp3"] {
+      match registry.resolve(input) {
+        Resolution::Found { command, args } => {
+          assert_eq!("sound", command.name());
+          assert_eq!(vec!["playlocal", "a.mp3"], args.collect::<Vec<_>>());
+        }
+        _ => panic!("expected command to be found. input={}", input),
+      }
+    }
+  }
+
+  #[test]
+  fn unknown_command() {
+    assert!(matches!(
+      registry().resolve("nope arg"),
+      Resolution::Unknown("nope")
+    ));
+  }
+
+  #[test]
+  fn empty_input() {
+    assert!(matches!(registry().resolve("   "), Resolution::Empty));
+  }
+
+  #[test]
+  fn suggests_closest_name() {
+    let registry = registry();
+
+    assert_eq!(Some("sound"), registry.suggest("sond"));
+    assert_eq!(Some("video"), registry.suggest("vidoe"));
+    assert_eq!(Some("s"), registry.suggest("x"));
+    assert_eq!(None, registry.suggest("chatbot"));
+  }
+
+  #[test]
+  fn test_strip_prefix() {
+    let bot_id = UserId(42);
+
+    let tests = vec![
+      ("b!help", "b!", Some("help")),
+      ("!help", "!", Some("help")),
+      ("b!help", "!", None),
+      ("<@42> help", "b!", Some(" help")),
+      ("<@!42>help", "b!", Some("help")),
+      ("<@43> help", "b!", None),
+      ("hello", "b!", None),
+    ];
+
+    for (content, prefix, expected) in tests {
+      assert_eq!(
+        expected,
+        strip_prefix(content, prefix, bot_id),
+        "content={}",
+        content
+      );
+    }
+  }
+
+  #[test]
+  fn test_levenshtein_distance() {
+    let tests = vec![
+      ("", "", 0),
+      ("abc", "", 3),
+      ("", "abc", 3),
+      ("video", "video", 0),
+      ("videoskip", "video", 4),
+      ("sond", "sound", 1),
+      ("chatbto", "chatbot", 2),
+      ("ção", "cão", 1),
+    ];
+
+    for (a, b, expected) in tests {
+      assert_eq!(expected, levenshtein_distance(a, b), "a={} b={}", a, b);
+    }
+  }
+
+  #[test]
+  fn rejects_duplicated_names() {
+    let mut registry = registry();
+
+    let result = registry.register(Arc::new(FakeCommand {
+      name: "other",
+      aliases: &["s"],
+    }));
+
+    assert_eq!(
+      Some(RegistryError::DuplicatedName("s".to_owned())),
+      result.err()
+    );
+    assert_eq!(2, registry.commands().count());
+  }
+
+  #[test]
+  fn usage_prefers_the_subcommand_usage() {
+    let tests = vec![
+      (
+        Some("playlocal"),
+        "sound playlocal <file> [--loop] [--volume=<0-100>]",
+      ),
+      (
+        None,
+        "sound <playlink|playlocal> <link|file> [--loop] [--volume=<0-100>]",
+      ),
+      (
+        Some("unknown"),
+        "sound <playlink|playlocal> <link|file> [--loop] [--volume=<0-100>]",
+      ),
+    ];
+
+    for (subcommand, expected) in tests {
+      assert_eq!(
+        expected,
+        usage(&SoundCommand, subcommand),
+        "subcommand={:?}",
+        subcommand
+      );
+    }
+  }
+}
@@ -0,0 +1,164 @@
+use std::{fmt::Write, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::{client::Context, model::id::RoleId};
+
+use super::{
+  ArgError, Args, Command, CommandOption, Invocation, OptionKind, PermissionLevel, Subcommand,
+};
+use crate::guild_settings::{GuildSettings, GuildSettingsStore};
+
+/// Maps guild roles to permission levels.
+pub struct PermissionsCommand {
+  settings: Arc<GuildSettingsStore>,
+}
+
+impl PermissionsCommand {
+  pub fn new(settings: Arc<GuildSettingsStore>) -> Self {
+    Self { settings }
+  }
+}
+
+#[async_trait]
+impl Command for PermissionsCommand {
+  fn name(&self) -> &'static str {
+    "permissions"
+  }
+
+  fn aliases(&self) -> &'static [&'static str] {
+    &["perms"]
+  }
+
+  fn description(&self) -> &'static str {
+    "Shows or changes which roles are DJs and admins"
+  }
+
+  fn usage(&self) -> &'static str {
+    "permissions [list|add <dj|admin> <role>|remove <dj|admin> <role>]"
+  }
+
+  fn subcommands(&self) -> &'static [Subcommand] {
+    const OPTIONS: &[CommandOption] = &[
+      CommandOption::new("level", "The permission level").choices(&["dj", "admin"]),
+      CommandOption::new("role", "The role mention or id").kind(OptionKind::Role),
+    ];
+
+    const SUBCOMMANDS: &[Subcommand] = &[
+      Subcommand {
+        name: "list",
+        usage: "permissions list",
+        description: "Shows the roles of each permission level",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "add",
+        usage: "permissions add <dj|admin> <role>",
+        description: "Gives a permission level to a role",
+        options: OPTIONS,
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "remove",
+        usage: "permissions remove <dj|admin> <role>",
+        description: "Takes a permission level from a role",
+        options: OPTIONS,
+        permission: PermissionLevel::Admin,
+      },
+    ];
+
+    SUBCOMMANDS
+  }
+
+  #[tracing::instrument(name = "permissions", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    let guild_id = match invocation.guild_id() {
+      None => {
+        invocation
+          .reply(ctx, "permissions can only be changed in a guild")
+          .await?;
+        return Ok(());
+      }
+      Some(guild_id) => guild_id,
+    };
+
+    let subcommand = args.next();
+
+    let add = match subcommand.as_deref() {
+      None | Some("list") => {
+        let settings = self.settings.get(guild_id).await?;
+        invocation.reply(ctx, list_roles(&settings)?).await?;
+        return Ok(());
+      }
+      Some("add") => true,
+      Some("remove") => false,
+      Some(subcommand) => return Err(ArgError::UnknownSubcommand(subcommand.to_owned()).into()),
+    };
+
+    let level: String = args.required("level")?;
+    let level = match level.as_str() {
+      "dj" => PermissionLevel::Dj,
+      "admin" => PermissionLevel::Admin,
+      _ => {
+        return Err(
+          ArgError::Invalid {
+            name: "level",
+            kind: "permission level (dj or admin)",
+            value: level,
+          }
+          .into(),
+        )
+      }
+    };
+
+    let role_id: RoleId = args.required("role")?;
+
+    let settings = self
+      .settings
+      .update(guild_id, |settings| {
+        let roles = match level {
+          PermissionLevel::Dj => &mut settings.dj_roles,
+          _ => &mut settings.admin_roles,
+        };
+
+        roles.retain(|role| *role != role_id);
+
+        if add {
+          roles.push(role_id);
+        }
+      })
+      .await?;
+
+    invocation.reply(ctx, list_roles(&settings)?).await?;
+
+    Ok(())
+  }
+}
+
+fn list_roles(settings: &GuildSettings) -> Result<String> {
+  let mut buffer = String::new();
+
+  for (level, roles) in [
+    (PermissionLevel::Dj, &settings.dj_roles),
+    (PermissionLevel::Admin, &settings.admin_roles),
+  ] {
+    let roles = if roles.is_empty() {
+      String::from("none")
+    } else {
+      roles
+        .iter()
+        .map(|role| format!("<@&{}>", role))
+        .collect::<Vec<_>>()
+        .join(", ")
+    };
+
+    writeln!(&mut buffer, "{}: {}", level, roles)?;
+  }
+
+  if settings.dj_roles.is_empty() {
+    writeln!(&mut buffer, "Only admins are DJs until a DJ role is added.")?;
+  }
+
+  Ok(buffer)
+}
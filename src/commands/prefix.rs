@@ -0,0 +1,165 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{ArgError, Args, Command, CommandOption, Invocation, PermissionLevel, Subcommand};
+use crate::guild_settings::GuildSettingsStore;
+
+/// The maximum number of characters in a prefix.
+const MAX_PREFIX_LEN: usize = 10;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+enum PrefixError {
+  #[error("the prefix cannot be empty")]
+  Empty,
+  #[error("the prefix cannot contain spaces")]
+  ContainsWhitespace,
+  #[error("the prefix cannot be longer than {MAX_PREFIX_LEN} characters")]
+  TooLong,
+}
+
+pub struct PrefixCommand {
+  settings: Arc<GuildSettingsStore>,
+}
+
+impl PrefixCommand {
+  pub fn new(settings: Arc<GuildSettingsStore>) -> Self {
+    Self { settings }
+  }
+}
+
+#[async_trait]
+impl Command for PrefixCommand {
+  fn name(&self) -> &'static str {
+    "prefix"
+  }
+
+  fn description(&self) -> &'static str {
+    "Shows or changes the prefix used by the bot in the guild"
+  }
+
+  fn usage(&self) -> &'static str {
+    "prefix [show|set <prefix>|reset]"
+  }
+
+  fn subcommands(&self) -> &'static [Subcommand] {
+    const SUBCOMMANDS: &[Subcommand] = &[
+      Subcommand {
+        name: "show",
+        usage: "prefix show",
+        description: "Shows the prefix used in the guild",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "set",
+        usage: "prefix set <prefix>",
+        description: "Changes the prefix used in the guild",
+        options: &[CommandOption::new("prefix", "The new prefix")],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "reset",
+        usage: "prefix reset",
+        description: "Goes back to using the default prefix",
+        options: &[],
+        permission: PermissionLevel::Admin,
+      },
+    ];
+
+    SUBCOMMANDS
+  }
+
+  #[tracing::instrument(name = "prefix", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    let guild_id = match invocation.guild_id() {
+      None => {
+        invocation
+          .reply(ctx, "the prefix can only be changed in a guild")
+          .await?;
+        return Ok(());
+      }
+      Some(guild_id) => guild_id,
+    };
+
+    let subcommand = args.next();
+
+    let new_prefix = match subcommand.as_deref() {
+      None | Some("show") => {
+        let prefix = self.settings.prefix(Some(guild_id)).await?;
+        invocation
+          .reply(ctx, format!("the prefix is `{}`", prefix))
+          .await?;
+        return Ok(());
+      }
+      Some("set") => {
+        let prefix: String = args.required("prefix")?;
+
+        if let Err(err) = validate_prefix(&prefix) {
+          invocation.reply(ctx, err.to_string()).await?;
+          return Ok(());
+        }
+
+        Some(prefix)
+      }
+      Some("reset") => None,
+      Some(subcommand) => return Err(ArgError::UnknownSubcommand(subcommand.to_owned()).into()),
+    };
+
+    let settings = self
+      .settings
+      .update(guild_id, |settings| settings.prefix = new_prefix)
+      .await?;
+
+    invocation
+      .reply(
+        ctx,
+        format!(
+          "the prefix is now `{}`",
+          settings.prefix.as_deref().unwrap_or(super::PREFIX)
+        ),
+      )
+      .await?;
+
+    Ok(())
+  }
+}
+
+fn validate_prefix(prefix: &str) -> Result<(), PrefixError> {
+  if prefix.is_empty() {
+    return Err(PrefixError::Empty);
+  }
+
+  if prefix.chars().any(char::is_whitespace) {
+    return Err(PrefixError::ContainsWhitespace);
+  }
+
+  if prefix.chars().count() > MAX_PREFIX_LEN {
+    return Err(PrefixError::TooLong);
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate_prefix() {
+    let tests = vec![
+      ("b!", Ok(())),
+      ("!", Ok(())),
+      ("", Err(PrefixError::Empty)),
+      ("b !", Err(PrefixError::ContainsWhitespace)),
+      ("ççççççççççç", Err(PrefixError::TooLong)),
+      ("çççççççççç", Ok(())),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(expected, validate_prefix(input), "input={}", input);
+    }
+  }
+}
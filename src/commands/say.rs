@@ -0,0 +1,198 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::{
+  client::Context,
+  model::{id::ChannelId, voice::VoiceState},
+};
+
+use super::{ArgError, Args, Command, CommandOption, Invocation};
+use crate::{audio, chatbot::ChatBot, tts::Tts};
+
+/// The most a discord message has, which is the most the chat bot reads.
+const MAX_TEXT_LENGTH: usize = 2000;
+
+pub struct SayCommand {
+  chatbot: Arc<ChatBot>,
+  tts: Arc<Tts>,
+}
+
+impl SayCommand {
+  pub fn new(chatbot: Arc<ChatBot>, tts: Arc<Tts>) -> Self {
+    Self { chatbot, tts }
+  }
+}
+
+#[async_trait]
+impl Command for SayCommand {
+  fn name(&self) -> &'static str {
+    "say"
+  }
+
+  fn description(&self) -> &'static str {
+    "Reads the text in the voice channel you are in"
+  }
+
+  fn usage(&self) -> &'static str {
+    "say [--voice=<voice>] <text>"
+  }
+
+  fn options(&self) -> &'static [CommandOption] {
+    const OPTIONS: &[CommandOption] = &[
+      CommandOption::new("text", "The text to read"),
+      CommandOption::new("voice", "The voice that reads the text").flag(),
+    ];
+
+    OPTIONS
+  }
+
+  #[tracing::instrument(name = "say", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, args: Args) -> Result<()> {
+    let (voice, text) = voice_and_text(args)?;
+
+    if text.chars().count() > MAX_TEXT_LENGTH {
+      invocation
+        .reply(
+          ctx,
+          format!(
+            "the text is too long, the most is {} characters",
+            MAX_TEXT_LENGTH
+          ),
+        )
+        .await?;
+      return Ok(());
+    }
+
+    let voice = match voice {
+      None => None,
+      Some(voice) => match self.tts.find_voice(&voice).await {
+        None => {
+          invocation
+            .reply(
+              ctx,
+              format!("{} is not a voice, use tts voices to see the voices", voice),
+            )
+            .await?;
+          return Ok(());
+        }
+        Some(voice) => Some(voice),
+      },
+    };
+
+    if say_channel(audio::user_voice_state(ctx, invocation).as_ref()).is_none() {
+      invocation.reply(ctx, "tu nao ta em call dog").await?;
+      return Ok(());
+    }
+
+    self.chatbot.say(ctx, invocation, &text, voice).await?;
+
+    Ok(())
+  }
+}
+
+/// Returns the voice channel the text is read in. Muted members can use say too,
+/// reading the text of members who cannot speak is what it is for.
+fn say_channel(voice_state: Option<&VoiceState>) -> Option<ChannelId> {
+  voice_state?.channel_id
+}
+
+/// Splits the arguments in the voice passed with `--voice=<voice>` or `--voice <voice>` and the text.
+fn voice_and_text(mut args: Args) -> Result<(Option<String>, String), ArgError> {
+  let voice = match args.flag_value::<String>("voice")? {
+    Some(voice) => Some(voice),
+    // `--voice <voice>` works too when it comes before the text.
+    None if args.peek() == Some("--voice") => {
+      args.next();
+      Some(args.required::<String>("voice")?)
+    }
+    None => None,
+  };
+
+  let text = args.rest();
+  if text.trim().is_empty() {
+    return Err(ArgError::Missing("text"));
+  }
+
+  Ok((voice, text))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn muted_members_can_say() {
+    let voice_state = |channel_id: Option<u64>, mute: bool, self_mute: bool, suppress: bool| {
+      serde_json::from_value::<VoiceState>(serde_json::json!({
+        "channel_id": channel_id.map(|id| id.to_string()),
+        "deaf": false,
+        "mute": mute,
+        "self_deaf": false,
+        "self_mute": self_mute,
+        "self_video": false,
+        "session_id": "session",
+        "suppress": suppress,
+        "user_id": "2",
+        "request_to_speak_timestamp": null,
+      }))
+      .unwrap()
+    };
+
+    for (mute, self_mute, suppress) in [
+      (false, false, false),
+      (true, false, false),
+      (false, true, false),
+      (false, false, true),
+    ] {
+      assert_eq!(
+        Some(ChannelId(1)),
+        say_channel(Some(&voice_state(Some(1), mute, self_mute, suppress))),
+        "mute={} self_mute={} suppress={}",
+        mute,
+        self_mute,
+        suppress
+      );
+    }
+
+    assert_eq!(
+      None,
+      say_channel(Some(&voice_state(None, true, false, false)))
+    );
+    assert_eq!(None, say_channel(None));
+  }
+
+  #[test]
+  fn parses_the_voice_and_the_text() {
+    assert_eq!(
+      Ok((None, String::from("hello there"))),
+      voice_and_text(Args::parse("hello there"))
+    );
+    assert_eq!(
+      Ok((Some(String::from("en-US")), String::from("hello there"))),
+      voice_and_text(Args::parse("--voice en-US hello there"))
+    );
+    assert_eq!(
+      Ok((Some(String::from("en-US")), String::from("hello there"))),
+      voice_and_text(Args::parse("hello --voice=en-US there"))
+    );
+    assert_eq!(
+      Err(ArgError::Missing("text")),
+      voice_and_text(Args::parse("--voice=en-US"))
+    );
+    // Text sent with the slash command is never a flag, the voice comes in its own option.
+    assert_eq!(
+      Ok((None, String::from("--voice=en-US hi"))),
+      voice_and_text(Args::from(vec![String::from("--voice=en-US hi")]))
+    );
+    let mut args = Args::from(vec![String::from("--voice=en-US hi")]);
+    args.push_flag(String::from("--voice=pt-BR"));
+    assert_eq!(
+      Ok((
+        Some(String::from("pt-BR")),
+        String::from("--voice=en-US hi")
+      )),
+      voice_and_text(args)
+    );
+  }
+}
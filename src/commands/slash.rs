@@ -0,0 +1,317 @@
+//! Registers the commands in the registry as discord slash commands and
+//! turns slash command options into the arguments the commands expect.
+
+use anyhow::Result;
+use serenity::builder::{CreateApplicationCommand, CreateApplicationCommandOption};
+use serenity::client::Context;
+use serenity::model::application::command::Command as ApplicationCommand;
+use serenity::model::application::command::CommandOptionType;
+use serenity::model::application::interaction::application_command::CommandDataOption;
+use tracing::info;
+
+use super::{Args, Command, CommandOption, OptionKind, Registry};
+
+/// Replaces the bot slash commands with the commands in the registry.
+#[tracing::instrument(name = "slash::register_commands", skip_all)]
+pub async fn register_commands(ctx: &Context, registry: &Registry) -> Result<()> {
+  let commands = ApplicationCommand::set_global_application_commands(&ctx.http, |builder| {
+    for command in registry.commands() {
+      builder.create_application_command(|builder| build_command(builder, command.as_ref()));
+    }
+    builder
+  })
+  .await?;
+
+  info!("registered slash commands. count={}", commands.len());
+
+  Ok(())
+}
+
+fn build_command<'a>(
+  builder: &'a mut CreateApplicationCommand,
+  command: &dyn Command,
+) -> &'a mut CreateApplicationCommand {
+  builder
+    .name(command.name())
+    .description(command.description());
+
+  if command.subcommands().is_empty() {
+    for option in command.options() {
+      builder.create_option(|builder| build_option(builder, option));
+    }
+  }
+
+  for subcommand in command.subcommands() {
+    builder.create_option(|builder| {
+      builder
+        .kind(CommandOptionType::SubCommand)
+        .name(subcommand.name)
+        .description(subcommand.description);
+
+      for option in subcommand.options {
+        builder.create_sub_option(|builder| build_option(builder, option));
+      }
+
+      builder
+    });
+  }
+
+  builder
+}
+
+fn build_option<'a>(
+  builder: &'a mut CreateApplicationCommandOption,
+  option: &CommandOption,
+) -> &'a mut CreateApplicationCommandOption {
+  builder
+    .kind(match option.kind {
+      OptionKind::String => CommandOptionType::String,
+      OptionKind::Integer => CommandOptionType::Integer,
+      OptionKind::Boolean => CommandOptionType::Boolean,
+      OptionKind::Role => CommandOptionType::Role,
+    })
+    .name(option.name)
+    .description(option.description)
+    .required(option.required)
+    .set_autocomplete(option.autocomplete);
+
+  if let Some((min, max)) = option.range {
+    builder.min_int_value(min).max_int_value(max);
+  }
+
+  for choice in option.choices {
+    builder.add_string_choice(choice, choice);
+  }
+
+  builder
+}
+
+/// Turns the options sent with a slash command into the arguments the command would receive
+/// if it had been invoked with the prefix. Example: `/sound playlocal file:a.mp3 loop:true` -> `playlocal a.mp3 --loop`.
+pub fn args_from_options(command: &dyn Command, options: &[CommandDataOption]) -> Args {
+  let subcommand = options
+    .iter()
+    .find(|option| option.kind == CommandOptionType::SubCommand);
+
+  match subcommand {
+    None => option_args(Vec::new(), command.options(), options),
+    Some(option) => {
+      let declared_options = command
+        .subcommands()
+        .iter()
+        .find(|subcommand| subcommand.name == option.name)
+        .map(|subcommand| subcommand.options)
+        .unwrap_or(&[]);
+
+      option_args(vec![option.name.clone()], declared_options, &option.options)
+    }
+  }
+}
+
+/// Adds the values of `options` to `values` in the order the options were declared because discord
+/// does not guarantee the options are sent in the same order. Flags go after the values.
+fn option_args(
+  mut values: Vec<String>,
+  declared_options: &[CommandOption],
+  options: &[CommandDataOption],
+) -> Args {
+  let mut flags = Vec::new();
+
+  for declared in declared_options {
+    let value = match options
+      .iter()
+      .find(|option| option.name == declared.name)
+      .and_then(|option| option.value.as_ref())
+    {
+      None => continue,
+      Some(value) => value,
+    };
+
+    let value = match value {
+      serde_json::Value::String(value) => value.clone(),
+      value => value.to_string(),
+    };
+
+    if !declared.flag {
+      values.push(value);
+    } else if declared.kind != OptionKind::Boolean {
+      flags.push(format!("--{}={}", declared.name, value));
+    } else if value == "true" {
+      flags.push(format!("--{}", declared.name));
+    }
+  }
+
+  let mut args = Args::from(values);
+  for flag in flags {
+    args.push_flag(flag);
+  }
+
+  args
+}
+
+/// Returns how the command is invoked as a slash command, the usage of the commands
+/// is written for the prefix and has flags that slash commands pass as options.
+/// Example: `/sound playlocal file:<file> [loop:<true|false>] [volume:<0-100>]`.
+pub fn usage(command: &dyn Command, subcommand: Option<&str>) -> String {
+  let subcommand = command
+    .subcommands()
+    .iter()
+    .find(|candidate| Some(candidate.name) == subcommand);
+
+  let mut usage = format!("/{}", command.name());
+
+  let options = match subcommand {
+    Some(subcommand) => {
+      usage.push(' ');
+      usage.push_str(subcommand.name);
+      subcommand.options
+    }
+    None if !command.subcommands().is_empty() => {
+      let names: Vec<_> = command
+        .subcommands()
+        .iter()
+        .map(|subcommand| subcommand.name)
+        .collect();
+      usage.push_str(&format!(" <{}>", names.join("|")));
+      &[]
+    }
+    None => command.options(),
+  };
+
+  for option in options {
+    let value = if !option.choices.is_empty() {
+      option.choices.join("|")
+    } else if let Some((min, max)) = option.range {
+      format!("{}-{}", min, max)
+    } else if option.kind == OptionKind::Boolean {
+      String::from("true|false")
+    } else {
+      option.name.to_owned()
+    };
+
+    if option.required {
+      usage.push_str(&format!(" {}:<{}>", option.name, value));
+    } else {
+      usage.push_str(&format!(" [{}:<{}>]", option.name, value));
+    }
+  }
+
+  usage
+}
+
+/// Returns the name and the current value of the option the user is typing.
+pub fn focused_option(options: &[CommandDataOption]) -> Option<(&str, &str)> {
+  options.iter().find_map(|option| {
+    if option.focused {
+      Some((
+        option.name.as_str(),
+        option
+          .value
+          .as_ref()
+          .and_then(|value| value.as_str())
+          .unwrap_or_default(),
+      ))
+    } else {
+      focused_option(&option.options)
+    }
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::commands::{EchoCommand, SoundCommand};
+
+  fn options(json: serde_json::Value) -> Vec<CommandDataOption> {
+    serde_json::from_value(json).unwrap()
+  }
+
+  #[test]
+  fn top_level_options_become_args() {
+    let options = options(serde_json::json!([
+      { "name": "text", "type": 3, "value": "hello world" }
+    ]));
+
+    assert_eq!(
+      Args::from(vec![String::from("hello world")]),
+      args_from_options(&EchoCommand, &options)
+    );
+  }
+
+  #[test]
+  fn subcommand_name_is_the_first_arg() {
+    let options = options(serde_json::json!([
+      {
+        "name": "playlocal",
+        "type": 1,
+        "options": [{ "name": "file", "type": 3, "value": "yo_zanders.mp3" }]
+      }
+    ]));
+
+    assert_eq!(
+      Args::from(vec![
+        String::from("playlocal"),
+        String::from("yo_zanders.mp3")
+      ]),
+      args_from_options(&SoundCommand, &options)
+    );
+  }
+
+  #[test]
+  fn flag_options_become_flags() {
+    let options = options(serde_json::json!([
+      {
+        "name": "playlocal",
+        "type": 1,
+        "options": [
+          { "name": "volume", "type": 4, "value": 50 },
+          { "name": "file", "type": 3, "value": "yo_zanders.mp3" },
+          { "name": "loop", "type": 5, "value": true }
+        ]
+      }
+    ]));
+
+    let mut args = args_from_options(&SoundCommand, &options);
+
+    assert!(args.flag("loop"));
+    assert_eq!(Ok(Some(50)), args.flag_value::<u8>("volume"));
+    assert_eq!(Some(String::from("playlocal")), args.next());
+    assert_eq!(Some(String::from("yo_zanders.mp3")), args.next());
+    assert_eq!(None, args.next());
+  }
+
+  #[test]
+  fn usage_shows_the_options_instead_of_flags() {
+    let tests = vec![
+      (
+        Some("playlocal"),
+        "/sound playlocal file:<file> [loop:<true|false>] [volume:<0-100>]",
+      ),
+      (None, "/sound <playlink|playlocal>"),
+    ];
+
+    for (subcommand, expected) in tests {
+      assert_eq!(
+        expected,
+        usage(&SoundCommand, subcommand),
+        "subcommand={:?}",
+        subcommand
+      );
+    }
+
+    assert_eq!("/echo text:<text>", usage(&EchoCommand, None));
+  }
+
+  #[test]
+  fn finds_focused_option_inside_subcommand() {
+    let options = options(serde_json::json!([
+      {
+        "name": "playlocal",
+        "type": 1,
+        "options": [{ "name": "file", "type": 3, "value": "yo", "focused": true }]
+      }
+    ]));
+
+    assert_eq!(Some(("file", "yo")), focused_option(&options));
+  }
+}
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Url;
+use serenity::client::Context;
+
+use super::{
+  ArgError, Args, Command, CommandOption, Invocation, OptionKind, PermissionLevel, Subcommand,
+};
+use crate::audio;
+
+pub struct SoundCommand;
+
+/// `--volume=100` plays the audio at its original volume.
+const MAX_VOLUME: u8 = 100;
+
+const LOOP_OPTION: CommandOption = CommandOption::new("loop", "Plays the audio again when it ends")
+  .kind(OptionKind::Boolean)
+  .flag();
+
+const VOLUME_OPTION: CommandOption = CommandOption::new("volume", "The volume from 0 to 100")
+  .kind(OptionKind::Integer)
+  .range(0, MAX_VOLUME as i64)
+  .flag();
+
+#[async_trait]
+impl Command for SoundCommand {
+  fn name(&self) -> &'static str {
+    "sound"
+  }
+
+  fn description(&self) -> &'static str {
+    "Plays audio in the voice channel you are in"
+  }
+
+  fn usage(&self) -> &'static str {
+    "sound <playlink|playlocal> <link|file> [--loop] [--volume=<0-100>]"
+  }
+
+  fn subcommands(&self) -> &'static [Subcommand] {
+    const SUBCOMMANDS: &[Subcommand] = &[
+      Subcommand {
+        name: "playlink",
+        usage: "sound playlink <link> [--loop] [--volume=<0-100>]",
+        description: "Plays the audio from a link",
+        options: &[
+          CommandOption::new("link", "The audio link"),
+          LOOP_OPTION,
+          VOLUME_OPTION,
+        ],
+        permission: PermissionLevel::Dj,
+      },
+      Subcommand {
+        name: "playlocal",
+        usage: "sound playlocal <file> [--loop] [--volume=<0-100>]",
+        description: "Plays one of the audio files the bot has",
+        options: &[
+          CommandOption::new("file", "The audio file name").autocomplete(),
+          LOOP_OPTION,
+          VOLUME_OPTION,
+        ],
+        permission: PermissionLevel::Everyone,
+      },
+    ];
+
+    SUBCOMMANDS
+  }
+
+  async fn autocomplete(&self, option: &str, value: &str) -> Result<Vec<String>> {
+    if option != "file" {
+      return Ok(vec![]);
+    }
+
+    Ok(
+      audio::local_audio_files()
+        .await?
+        .into_iter()
+        .filter(|file_name| file_name.contains(value))
+        .collect(),
+    )
+  }
+
+  #[tracing::instrument(name = "sound", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    let sub_command = args.next().unwrap_or_else(|| String::from("playlink"));
+
+    let looped = args.flag("loop");
+    let volume = match args.flag_value::<u8>("volume")? {
+      Some(volume) if volume > MAX_VOLUME => {
+        return Err(
+          ArgError::Invalid {
+            name: "volume",
+            kind: "volume between 0 and 100",
+            value: volume.to_string(),
+          }
+          .into(),
+        )
+      }
+      volume => volume,
+    };
+
+    let track_handle = match sub_command.as_str() {
+      "playlink" => {
+        let link: Url = args.required("link")?;
+        Some(audio::play_audio(ctx, invocation, link.as_str()).await?)
+      }
+      "playlocal" => {
+        let file_name: String = args.required("file")?;
+        audio::play_local_audio(ctx, invocation, &file_name).await?
+      }
+      _ => return Err(ArgError::UnknownSubcommand(sub_command).into()),
+    };
+
+    if let Some(track_handle) = track_handle {
+      if looped {
+        track_handle.enable_loop()?;
+      }
+
+      if let Some(volume) = volume {
+        track_handle.set_volume(f32::from(volume) / f32::from(MAX_VOLUME))?;
+      }
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{ArgError, Args, Command, CommandOption, Invocation, PermissionLevel, Subcommand};
+use crate::{
+  contracts::tts::TextToSpeech,
+  tts::{voice::VoiceStore, Tts},
+};
+
+/// Lists longer than this are sent as a file because they do not fit in a message.
+const MAX_VOICES_REPLY_LENGTH: usize = 1900;
+
+pub struct TtsCommand {
+  tts: Arc<Tts>,
+  voices: Arc<VoiceStore>,
+}
+
+impl TtsCommand {
+  pub fn new(tts: Arc<Tts>, voices: Arc<VoiceStore>) -> Self {
+    Self { tts, voices }
+  }
+
+  /// Parses the voice argument, `Ok(None)` means the default voice and
+  /// `Err` is the reply sent when the voice does not exist.
+  async fn voice_arg(&self, voice: &str) -> Result<Option<String>, String> {
+    if voice == "default" {
+      return Ok(None);
+    }
+
+    match self.tts.find_voice(voice).await {
+      None => Err(format!(
+        "{} is not a voice, use tts voices to see the voices",
+        voice
+      )),
+      Some(voice) => Ok(Some(voice)),
+    }
+  }
+}
+
+#[async_trait]
+impl Command for TtsCommand {
+  fn name(&self) -> &'static str {
+    "tts"
+  }
+
+  fn description(&self) -> &'static str {
+    "Manages the text to speech"
+  }
+
+  fn usage(&self) -> &'static str {
+    "tts [voice [<voice>|default]|guildvoice [<voice>|default]|voices|cache <stats|clear>]"
+  }
+
+  fn subcommands(&self) -> &'static [Subcommand] {
+    const SUBCOMMANDS: &[Subcommand] = &[
+      Subcommand {
+        name: "voice",
+        usage: "tts voice [<voice>|default]",
+        description: "Shows or changes the voice the bot speaks to you with",
+        options: &[CommandOption::new("voice", "A voice from tts voices, or default").optional()],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "guildvoice",
+        usage: "tts guildvoice [<voice>|default]",
+        description: "Shows or changes the voice used in the guild",
+        options: &[CommandOption::new("voice", "A voice from tts voices, or default").optional()],
+        permission: PermissionLevel::Admin,
+      },
+      Subcommand {
+        name: "voices",
+        usage: "tts voices",
+        description: "Lists the voices of the text to speech engine",
+        options: &[],
+        permission: PermissionLevel::Everyone,
+      },
+      Subcommand {
+        name: "cache",
+        usage: "tts cache <stats|clear>",
+        description: "Shows or deletes the audio kept so the same text is not read again",
+        options: &[CommandOption::new("action", "stats or clear").choices(&["stats", "clear"])],
+        permission: PermissionLevel::Admin,
+      },
+    ];
+
+    SUBCOMMANDS
+  }
+
+  #[tracing::instrument(name = "tts", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    match args.next().as_deref() {
+      None | Some("voice") => {
+        let user_id = invocation.author().id;
+
+        let reply = match args.optional::<String>("voice")? {
+          None => {
+            let voice = match self
+              .voices
+              .voice(invocation.guild_id(), user_id, None)
+              .await?
+            {
+              None => self.tts.default_voice(),
+              Some(voice) => voice,
+            };
+            format!("your voice is {}", voice)
+          }
+          Some(voice) => match self.voice_arg(&voice).await {
+            Err(reply) => reply,
+            Ok(voice) => {
+              self.voices.set_user_voice(user_id, voice.clone()).await?;
+              match voice {
+                None => String::from("you no longer have your own voice"),
+                Some(voice) => format!("your voice is now {}", voice),
+              }
+            }
+          },
+        };
+
+        invocation.reply(ctx, reply).await?;
+      }
+      Some("guildvoice") => {
+        let guild_id = match invocation.guild_id() {
+          None => {
+            invocation
+              .reply(ctx, "the guild voice can only be changed in a guild")
+              .await?;
+            return Ok(());
+          }
+          Some(guild_id) => guild_id,
+        };
+
+        let reply = match args.optional::<String>("voice")? {
+          None => match self.voices.guild_voice(Some(guild_id)).await? {
+            None => format!(
+              "the guild uses the default voice, {}",
+              self.tts.default_voice()
+            ),
+            Some(voice) => format!("the guild voice is {}", voice),
+          },
+          Some(voice) => match self.voice_arg(&voice).await {
+            Err(reply) => reply,
+            Ok(voice) => {
+              self.voices.set_guild_voice(guild_id, voice.clone()).await?;
+              match voice {
+                None => format!(
+                  "the guild voice is now the default voice, {}",
+                  self.tts.default_voice()
+                ),
+                Some(voice) => format!("the guild voice is now {}", voice),
+              }
+            }
+          },
+        };
+
+        invocation.reply(ctx, reply).await?;
+      }
+      Some("voices") => {
+        let voices = self.tts.voices().await?;
+
+        if voices.is_empty() {
+          invocation.reply(ctx, "the engine has no voices").await?;
+          return Ok(());
+        }
+
+        let reply = voices.join(", ");
+        if reply.len() <= MAX_VOICES_REPLY_LENGTH {
+          invocation.reply(ctx, reply).await?;
+        } else {
+          invocation
+            .reply_with_file(
+              ctx,
+              format!("{} voices", voices.len()),
+              "voices.txt",
+              voices.join("\n").into_bytes(),
+            )
+            .await?;
+        }
+      }
+      Some("cache") => {
+        let reply = match args.required::<String>("action")?.as_str() {
+          "stats" => {
+            let stats = self.tts.cache_stats().await?;
+            format!(
+              "audios: {}\nsize: {:.1} MB\nhits: {}\nmisses: {}",
+              stats.entries,
+              stats.size_bytes as f64 / (1024.0 * 1024.0),
+              stats.hits,
+              stats.misses
+            )
+          }
+          "clear" => {
+            let deleted = self.tts.clear_cache().await?;
+            format!("{} audios deleted", deleted)
+          }
+          action => {
+            return Err(
+              ArgError::Invalid {
+                name: "action",
+                kind: "action (stats or clear)",
+                value: action.to_owned(),
+              }
+              .into(),
+            )
+          }
+        };
+
+        invocation.reply(ctx, reply).await?;
+      }
+      Some(subcommand) => return Err(ArgError::UnknownSubcommand(subcommand.to_owned()).into()),
+    }
+
+    Ok(())
+  }
+}
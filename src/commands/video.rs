@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Url;
+use serenity::client::Context;
+
+use super::{Args, Command, CommandOption, Invocation, PermissionLevel};
+use crate::video::Video;
+
+pub struct VideoCommand {
+  video: Arc<Video>,
+}
+
+impl VideoCommand {
+  pub fn new(video: Arc<Video>) -> Self {
+    Self { video }
+  }
+}
+
+#[async_trait]
+impl Command for VideoCommand {
+  fn name(&self) -> &'static str {
+    "video"
+  }
+
+  fn description(&self) -> &'static str {
+    "Adds a youtube, twitch or stremio video to the queue of videos streamed in the voice channel"
+  }
+
+  fn usage(&self) -> &'static str {
+    "video <url>"
+  }
+
+  fn permission(&self) -> PermissionLevel {
+    PermissionLevel::Dj
+  }
+
+  fn options(&self) -> &'static [CommandOption] {
+    const OPTIONS: &[CommandOption] = &[CommandOption::new("url", "The video url")];
+
+    OPTIONS
+  }
+
+  #[tracing::instrument(name = "video", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, mut args: Args) -> Result<()> {
+    let url: Url = args.required("url")?;
+    self.video.play(ctx, invocation, url.as_str()).await
+  }
+}
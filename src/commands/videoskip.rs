@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{Args, Command, Invocation, PermissionLevel};
+use crate::video::Video;
+
+pub struct VideoSkipCommand {
+  video: Arc<Video>,
+}
+
+impl VideoSkipCommand {
+  pub fn new(video: Arc<Video>) -> Self {
+    Self { video }
+  }
+}
+
+#[async_trait]
+impl Command for VideoSkipCommand {
+  fn name(&self) -> &'static str {
+    "videoskip"
+  }
+
+  fn description(&self) -> &'static str {
+    "Skips the video being streamed"
+  }
+
+  fn usage(&self) -> &'static str {
+    "videoskip"
+  }
+
+  fn permission(&self) -> PermissionLevel {
+    PermissionLevel::Dj
+  }
+
+  #[tracing::instrument(name = "videoskip", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, _args: Args) -> Result<()> {
+    self.video.skip_current_video(ctx, invocation).await
+  }
+}
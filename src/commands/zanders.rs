@@ -0,0 +1,30 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serenity::client::Context;
+
+use super::{Args, Command, Invocation};
+use crate::audio;
+
+pub struct ZandersCommand;
+
+#[async_trait]
+impl Command for ZandersCommand {
+  fn name(&self) -> &'static str {
+    "zanders"
+  }
+
+  fn description(&self) -> &'static str {
+    "Yo zanders"
+  }
+
+  fn usage(&self) -> &'static str {
+    "zanders"
+  }
+
+  #[tracing::instrument(name = "zanders", skip_all)]
+  async fn execute(&self, ctx: &Context, invocation: &Invocation, _args: Args) -> Result<()> {
+    audio::play_local_audio(ctx, invocation, "yo_zanders.mp3").await?;
+
+    Ok(())
+  }
+}
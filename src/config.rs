@@ -0,0 +1,782 @@
+//! The bot configuration. It is read from a TOML file and every value can be overridden
+//! by the environment variable listed in [Config::apply_env_overrides].
+//! Changes to the file are applied while the bot is running by [ConfigReloader].
+
+use std::{
+  collections::{BTreeMap, HashSet},
+  path::{Path, PathBuf},
+  str::FromStr,
+  sync::{Arc, Mutex, Weak},
+  time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tracing::{error, info, warn};
+
+use crate::{
+  chatbot, infra::cache::redis, rate_limit, text_generation, translation, tts, video_stream_api,
+};
+
+/// Used when the `CONFIG_PATH` environment variable is not set.
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub discord: DiscordConfig,
+  pub redis: redis::Config,
+  pub text_generation: text_generation::Config,
+  pub translation: translation::Config,
+  pub chatbot: chatbot::Config,
+  pub tts: tts::Config,
+  pub video_stream_api: video_stream_api::Config,
+  pub rate_limit: rate_limit::Config,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscordConfig {
+  pub token: String,
+  pub app_id: u64,
+  /// Token of the user account that streams videos in voice channels.
+  pub self_bot_token: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum ConfigError {
+  #[error("unable to read config file {path}: {message}")]
+  Read { path: String, message: String },
+  #[error("config file is not valid TOML: {0}")]
+  Parse(String),
+  #[error("invalid config:\n{}", .0.iter().map(|problem| format!("  - {}", problem)).collect::<Vec<_>>().join("\n"))]
+  Invalid(Vec<String>),
+}
+
+impl Config {
+  /// Reads the config file at `path` and applies the environment variable overrides.
+  /// The file is optional because every value can come from the environment.
+  #[tracing::instrument(name = "Config::load", skip_all, fields(path = %path.display()))]
+  pub async fn load(path: &Path) -> Result<Self, ConfigError> {
+    let contents = match tokio::fs::read_to_string(path).await {
+      Ok(contents) => contents,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+      Err(err) => {
+        return Err(ConfigError::Read {
+          path: path.display().to_string(),
+          message: err.to_string(),
+        })
+      }
+    };
+
+    Self::from_sources(&contents, |key| std::env::var(key).ok())
+  }
+
+  /// Builds the config from the contents of a config file and the environment.
+  /// Every problem is reported at once instead of failing at the first one.
+  pub fn from_sources(
+    contents: &str,
+    env: impl Fn(&str) -> Option<String>,
+  ) -> Result<Self, ConfigError> {
+    let mut config: Config =
+      toml::from_str(contents).map_err(|err| ConfigError::Parse(err.to_string()))?;
+
+    let mut problems = Vec::new();
+
+    config.apply_env_overrides(&env, &mut problems);
+    config.validate(&mut problems);
+
+    if problems.is_empty() {
+      Ok(config)
+    } else {
+      Err(ConfigError::Invalid(problems))
+    }
+  }
+
+  fn apply_env_overrides(
+    &mut self,
+    env: &impl Fn(&str) -> Option<String>,
+    problems: &mut Vec<String>,
+  ) {
+    // Empty variables, like the ones in .env.example, keep the value from the file.
+    let mut set = |key: &str, target: &mut dyn FnMut(&str) -> bool| {
+      if let Some(value) = env(key).filter(|value| !value.is_empty()) {
+        if !target(&value) {
+          problems.push(format!("{} has an invalid value: {:?}", key, value));
+        }
+      }
+    };
+
+    set("DISCORD_TOKEN", &mut parse_into(&mut self.discord.token));
+    set("DISCORD_APP_ID", &mut parse_into(&mut self.discord.app_id));
+    set("DISCORD_SELF_BOT_TOKEN", &mut |value| {
+      self.discord.self_bot_token = Some(value.to_owned());
+      true
+    });
+    set("REDIS_HOST", &mut parse_into(&mut self.redis.host));
+    set("REDIS_PORT", &mut parse_into(&mut self.redis.port));
+    set("REDIS_PASSWORD", &mut parse_into(&mut self.redis.password));
+    set(
+      "CHAIML_DEVELOPER_UUID",
+      &mut parse_into(&mut self.text_generation.chaiml.chaiml_developer_uuid),
+    );
+    set(
+      "CHAIML_KEY",
+      &mut parse_into(&mut self.text_generation.chaiml.chaiml_key),
+    );
+    set(
+      "OPENAI_API_KEY",
+      &mut parse_into(&mut self.text_generation.openai.api_key),
+    );
+    set(
+      "LIBRETRANSLATE_API_KEY",
+      &mut parse_into(&mut self.translation.libretranslate.api_key),
+    );
+    set(
+      "DEEPL_API_KEY",
+      &mut parse_into(&mut self.translation.deepl.api_key),
+    );
+    set(
+      "CHAIML_INITIAL_CONTEXT",
+      &mut parse_into(&mut self.chatbot.initial_context),
+    );
+    set(
+      "VIDEO_STREAM_API_PORT",
+      &mut parse_into(&mut self.video_stream_api.port),
+    );
+  }
+
+  fn validate(&self, problems: &mut Vec<String>) {
+    let mut require = |is_set: bool, name: &str, env_var: &str| {
+      if !is_set {
+        problems.push(format!("{} is required (env: {})", name, env_var));
+      }
+    };
+
+    require(
+      !self.discord.token.is_empty(),
+      "discord.token",
+      "DISCORD_TOKEN",
+    );
+    require(self.discord.app_id != 0, "discord.app_id", "DISCORD_APP_ID");
+    require(!self.redis.host.is_empty(), "redis.host", "REDIS_HOST");
+    require(self.redis.port != 0, "redis.port", "REDIS_PORT");
+
+    let generation = &self.text_generation;
+    if generation.uses(text_generation::Backend::Chaiml) {
+      require(
+        !generation.chaiml.chaiml_developer_uuid.is_empty(),
+        "text_generation.chaiml.developer_uuid",
+        "CHAIML_DEVELOPER_UUID",
+      );
+      require(
+        !generation.chaiml.chaiml_key.is_empty(),
+        "text_generation.chaiml.key",
+        "CHAIML_KEY",
+      );
+    }
+    require(
+      self.chatbot.defines_default_persona() || !self.chatbot.initial_context.is_empty(),
+      "chatbot.initial_context",
+      "CHAIML_INITIAL_CONTEXT",
+    );
+    require(
+      self.video_stream_api.port != 0,
+      "video_stream_api.port",
+      "VIDEO_STREAM_API_PORT",
+    );
+    if self
+      .translation
+      .providers
+      .contains(&translation::Provider::Deepl)
+    {
+      require(
+        !self.translation.deepl.api_key.is_empty(),
+        "translation.deepl.api_key",
+        "DEEPL_API_KEY",
+      );
+    }
+
+    if self.translation.providers.is_empty() {
+      problems.push("translation.providers must have at least one provider".to_owned());
+    }
+    if self
+      .translation
+      .providers
+      .contains(&translation::Provider::Libretranslate)
+      && self.translation.libretranslate.base_url.is_empty()
+    {
+      problems.push("translation.libretranslate.base_url is required".to_owned());
+    }
+    if self.tts.engine == tts::Engine::Http && self.tts.http.url.is_empty() {
+      problems.push("tts.http.url is required".to_owned());
+    }
+    if self.tts.voice.is_empty() {
+      problems.push("tts.voice is required".to_owned());
+    }
+
+    for (name, backend, base_url, model) in [
+      (
+        "openai",
+        text_generation::Backend::Openai,
+        &generation.openai.base_url,
+        &generation.openai.model,
+      ),
+      (
+        "ollama",
+        text_generation::Backend::Ollama,
+        &generation.ollama.base_url,
+        &generation.ollama.model,
+      ),
+    ] {
+      if !generation.uses(backend) {
+        continue;
+      }
+      if base_url.is_empty() {
+        problems.push(format!("text_generation.{}.base_url is required", name));
+      }
+      if model.is_empty() {
+        problems.push(format!("text_generation.{}.model is required", name));
+      }
+    }
+
+    for problem in generation.profile.validate() {
+      problems.push(format!("text_generation.profile.{}", problem));
+    }
+
+    let mut persona_names = HashSet::new();
+    for persona in self.chatbot.personas() {
+      if !persona_names.insert(persona.name.to_lowercase()) {
+        problems.push(format!(
+          "chatbot.personas has more than one persona called {}",
+          persona.name
+        ));
+      }
+      for problem in persona.validate() {
+        problems.push(format!("chatbot.personas.{}: {}", persona.name, problem));
+      }
+    }
+
+    if self.chatbot.memory.max_tokens == 0 {
+      problems.push("chatbot.memory.max_tokens must be greater than 0".to_owned());
+    }
+
+    for guild_id in generation.guild_backends.keys() {
+      if guild_id.parse::<u64>().is_err() {
+        problems.push(format!(
+          "text_generation.guild_backends has an invalid guild id: {:?}",
+          guild_id
+        ));
+      }
+    }
+
+    for (name, quota) in [
+      ("rate_limit.user", &self.rate_limit.user),
+      ("rate_limit.channel", &self.rate_limit.channel),
+      ("rate_limit.command", &self.rate_limit.command),
+    ] {
+      if quota.capacity == 0 {
+        problems.push(format!("{}.capacity must be greater than 0", name));
+      }
+      if quota.refill_interval.is_zero() {
+        problems.push(format!(
+          "{}.refill_interval_secs must be greater than 0",
+          name
+        ));
+      }
+    }
+  }
+}
+
+/// Returns a function that parses a value into `target` and returns false if the value is invalid.
+fn parse_into<T: FromStr>(target: &mut T) -> impl FnMut(&str) -> bool + '_ {
+  move |value| match value.parse() {
+    Err(_) => false,
+    Ok(value) => {
+      *target = value;
+      true
+    }
+  }
+}
+
+/// Serializes a duration as a number of seconds, like `1.5`.
+pub fn serialize_seconds<S: Serializer>(
+  duration: &Duration,
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// Deserializes a duration written as a number of seconds, like `1.5`.
+pub fn deserialize_seconds<'de, D: Deserializer<'de>>(
+  deserializer: D,
+) -> Result<Duration, D::Error> {
+  let seconds = f64::deserialize(deserializer)?;
+
+  if !seconds.is_finite() || seconds < 0.0 {
+    return Err(serde::de::Error::custom(format!(
+      "expected a positive number of seconds but got {}",
+      seconds
+    )));
+  }
+
+  Ok(Duration::from_secs_f64(seconds))
+}
+
+/// A part of the bot that applies config changes while the bot is running.
+pub trait Reloadable: Send + Sync {
+  fn reload(&self, config: &Config);
+}
+
+/// Config keys that are only read when the bot starts.
+const RESTART_REQUIRED_PREFIXES: [&str; 3] = ["discord.", "redis.", "video_stream_api."];
+
+/// Values of config keys that end with these are not logged.
+const SECRET_SUFFIXES: [&str; 3] = ["token", "key", "password"];
+
+/// Returns a description of each config value that's different in `new`.
+pub fn changes(old: &Config, new: &Config) -> Vec<String> {
+  let (old, new) = (flatten(old), flatten(new));
+
+  let keys: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+
+  keys
+    .into_iter()
+    .filter(|key| old.get(*key) != new.get(*key))
+    .map(|key| {
+      let value = |values: &BTreeMap<String, serde_json::Value>| {
+        values
+          .get(key)
+          .map(|value| value.to_string())
+          .unwrap_or_else(|| String::from("unset"))
+      };
+
+      let mut change = if SECRET_SUFFIXES.iter().any(|suffix| key.ends_with(suffix)) {
+        format!("{} changed", key)
+      } else {
+        format!("{}: {} -> {}", key, value(&old), value(&new))
+      };
+
+      if RESTART_REQUIRED_PREFIXES
+        .iter()
+        .any(|prefix| key.starts_with(prefix))
+      {
+        change.push_str(" (takes effect after a restart)");
+      }
+
+      change
+    })
+    .collect()
+}
+
+/// Maps every config value to its dotted key, like `rate_limit.user.capacity`.
+fn flatten(config: &Config) -> BTreeMap<String, serde_json::Value> {
+  fn visit(
+    prefix: &str,
+    value: serde_json::Value,
+    values: &mut BTreeMap<String, serde_json::Value>,
+  ) {
+    match value {
+      serde_json::Value::Object(fields) => {
+        for (name, value) in fields {
+          let key = if prefix.is_empty() {
+            name
+          } else {
+            format!("{}.{}", prefix, name)
+          };
+          visit(&key, value, values);
+        }
+      }
+      serde_json::Value::Null => {}
+      value => {
+        values.insert(prefix.to_owned(), value);
+      }
+    }
+  }
+
+  let mut values = BTreeMap::new();
+  // Config only contains values that can be represented as json.
+  visit("", serde_json::to_value(config).unwrap(), &mut values);
+  values
+}
+
+/// Reloads the config file when it changes and applies the new config to the bot.
+pub struct ConfigReloader {
+  path: PathBuf,
+  current: Mutex<Config>,
+  /// When the config file was last modified, used to detect changes.
+  last_modified: Mutex<Option<SystemTime>>,
+  subscribers: Vec<Arc<dyn Reloadable>>,
+}
+
+/// How often the config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+impl ConfigReloader {
+  pub async fn new(
+    path: PathBuf,
+    config: Config,
+    subscribers: Vec<Arc<dyn Reloadable>>,
+  ) -> Arc<Self> {
+    let last_modified = modified_at(&path).await;
+
+    Arc::new(Self {
+      path,
+      current: Mutex::new(config),
+      last_modified: Mutex::new(last_modified),
+      subscribers,
+    })
+  }
+
+  /// Starts checking the config file for changes in the background.
+  pub fn watch(self: &Arc<Self>) {
+    tokio::spawn(watch_config_file(Arc::downgrade(self)));
+  }
+
+  /// Loads the config again and applies it if it is valid.
+  /// Returns what changed, the current config is kept if the new one is not valid.
+  #[tracing::instrument(name = "ConfigReloader::reload", skip_all)]
+  pub async fn reload(&self) -> Result<Vec<String>, ConfigError> {
+    *self.last_modified.lock().unwrap() = modified_at(&self.path).await;
+
+    let config = Config::load(&self.path).await?;
+
+    let mut current = self.current.lock().unwrap();
+
+    let changes = changes(&current, &config);
+
+    if changes.is_empty() {
+      info!("config did not change");
+      return Ok(changes);
+    }
+
+    for change in changes.iter() {
+      if change.ends_with("(takes effect after a restart)") {
+        warn!("config changed. change={}", change);
+      } else {
+        info!("config changed. change={}", change);
+      }
+    }
+
+    for subscriber in self.subscribers.iter() {
+      subscriber.reload(&config);
+    }
+
+    *current = config;
+
+    Ok(changes)
+  }
+
+  async fn was_modified(&self) -> bool {
+    let modified_at = modified_at(&self.path).await;
+    *self.last_modified.lock().unwrap() != modified_at
+  }
+}
+
+async fn modified_at(path: &Path) -> Option<SystemTime> {
+  tokio::fs::metadata(path)
+    .await
+    .and_then(|metadata| metadata.modified())
+    .ok()
+}
+
+#[tracing::instrument(name = "config::watch_config_file", skip_all)]
+async fn watch_config_file(reloader: Weak<ConfigReloader>) {
+  loop {
+    tokio::time::sleep(WATCH_INTERVAL).await;
+
+    match reloader.upgrade() {
+      None => {
+        info!("ConfigReloader has been dropped, quitting");
+        break;
+      }
+      Some(reloader) => {
+        if reloader.was_modified().await {
+          if let Err(err) = reloader.reload().await {
+            error!("unable to reload config. error={}", err);
+          }
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::collections::HashMap;
+
+  use super::*;
+
+  const CONFIG: &str = r#"
+    [discord]
+    token = "token"
+    app_id = 1
+
+    [redis]
+    host = "localhost"
+    port = 6379
+    password = "password"
+
+    [text_generation.chaiml]
+    developer_uuid = "uuid"
+    key = "key"
+
+    [chatbot]
+    initial_context = "Eliza: hi"
+
+    [video_stream_api]
+    port = 3000
+
+    [rate_limit.user]
+    capacity = 2
+    refill_interval_secs = 0.5
+  "#;
+
+  fn env(vars: &[(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+    let vars: HashMap<_, _> = vars.iter().copied().collect();
+    move |key| vars.get(key).map(|value| value.to_string())
+  }
+
+  #[test]
+  fn reads_the_config_file() {
+    let config = Config::from_sources(CONFIG, env(&[])).unwrap();
+
+    assert_eq!("token", config.discord.token);
+    assert_eq!(None, config.discord.self_bot_token);
+    assert_eq!(6379, config.redis.port);
+    assert_eq!("uuid", config.text_generation.chaiml.chaiml_developer_uuid);
+    assert_eq!(
+      rate_limit::Quota::new(2, Duration::from_millis(500)),
+      config.rate_limit.user
+    );
+    // Sections that are not in the file use the defaults.
+    assert_eq!(
+      rate_limit::Config::default().channel,
+      config.rate_limit.channel
+    );
+  }
+
+  #[test]
+  fn env_vars_override_the_config_file() {
+    let config = Config::from_sources(
+      CONFIG,
+      env(&[
+        ("DISCORD_TOKEN", "other token"),
+        ("REDIS_PORT", "1234"),
+        ("DISCORD_SELF_BOT_TOKEN", "self bot token"),
+      ]),
+    )
+    .unwrap();
+
+    assert_eq!("other token", config.discord.token);
+    assert_eq!(1234, config.redis.port);
+    assert_eq!(
+      Some("self bot token"),
+      config.discord.self_bot_token.as_deref()
+    );
+  }
+
+  #[test]
+  fn empty_env_vars_do_not_override_the_config_file() {
+    let config = Config::from_sources(
+      CONFIG,
+      env(&[
+        ("DISCORD_TOKEN", ""),
+        ("REDIS_PORT", ""),
+        ("DISCORD_SELF_BOT_TOKEN", ""),
+      ]),
+    )
+    .unwrap();
+
+    assert_eq!("token", config.discord.token);
+    assert_eq!(6379, config.redis.port);
+    assert_eq!(None, config.discord.self_bot_token);
+  }
+
+  #[test]
+  fn reports_every_problem_at_once() {
+    let result = Config::from_sources(
+      "[redis]\nhost = \"localhost\"",
+      env(&[("REDIS_PORT", "abc"), ("CHAIML_KEY", "key")]),
+    );
+
+    assert_eq!(
+      Err(ConfigError::Invalid(vec![
+        "REDIS_PORT has an invalid value: \"abc\"".to_owned(),
+        "discord.token is required (env: DISCORD_TOKEN)".to_owned(),
+        "discord.app_id is required (env: DISCORD_APP_ID)".to_owned(),
+        "redis.port is required (env: REDIS_PORT)".to_owned(),
+        "text_generation.chaiml.developer_uuid is required (env: CHAIML_DEVELOPER_UUID)".to_owned(),
+        "chatbot.initial_context is required (env: CHAIML_INITIAL_CONTEXT)".to_owned(),
+        "video_stream_api.port is required (env: VIDEO_STREAM_API_PORT)".to_owned(),
+      ])),
+      result
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_fields() {
+    assert!(matches!(
+      Config::from_sources("[discord]\ntokne = \"typo\"", env(&[])),
+      Err(ConfigError::Parse(_))
+    ));
+  }
+
+  #[test]
+  fn requires_the_settings_of_the_text_generation_backends_in_use() {
+    let config = CONFIG.replace(
+      "[text_generation.chaiml]",
+      "[text_generation.guild_backends]\n    1 = \"ollama\"\n\n    [text_generation.chaiml]",
+    );
+
+    assert_eq!(
+      Err(ConfigError::Invalid(vec![
+        "text_generation.ollama.model is required".to_owned()
+      ])),
+      Config::from_sources(&config, env(&[]))
+    );
+
+    // The ChaiML credentials are not needed when ChaiML is not used.
+    let config = CONFIG
+      .replace("developer_uuid = \"uuid\"", "")
+      .replace("key = \"key\"", "")
+      .replace(
+        "[text_generation.chaiml]",
+        "[text_generation]\n    backend = \"openai\"",
+      );
+    assert!(Config::from_sources(&config, env(&[])).is_ok());
+  }
+
+  #[test]
+  fn reads_the_personas() {
+    let config = format!(
+      "{}{}",
+      CONFIG,
+      r#"
+    [[chatbot.personas]]
+    name = "Pirate"
+    initial_context = "Pirate: arr"
+    language = "en"
+
+    [chatbot.personas.profile]
+    temperature = 1.2
+
+    [[chatbot.personas]]
+    name = "eliza"
+    top_k = 1
+    "#
+    );
+
+    // Sampling parameters go in the profile table.
+    assert!(matches!(
+      Config::from_sources(&config, env(&[])),
+      Err(ConfigError::Parse(_))
+    ));
+
+    let config = config.replace("    top_k = 1\n", "");
+    assert_eq!(
+      Err(ConfigError::Invalid(vec![
+        "chatbot.personas has more than one persona called Pirate".to_owned()
+      ])),
+      Config::from_sources(&config.replace("\"eliza\"", "\"Pirate\""), env(&[]))
+    );
+
+    let config = Config::from_sources(&config, env(&[])).unwrap();
+    let personas = config.chatbot.personas();
+    assert_eq!(
+      Some(&1.2),
+      personas[0]
+        .profile
+        .get(&crate::text_generation::Parameter::Temperature)
+    );
+    // The default persona is in the personas, so the initial context is not used.
+    assert_eq!(2, personas.len());
+    assert_eq!("", personas[1].initial_context);
+  }
+
+  #[test]
+  fn example_config_is_valid() {
+    let config = Config::from_sources(
+      include_str!("../config.example.toml"),
+      env(&[
+        ("DISCORD_TOKEN", "token"),
+        ("DISCORD_APP_ID", "1"),
+        ("CHAIML_DEVELOPER_UUID", "uuid"),
+        ("CHAIML_KEY", "key"),
+        ("CHAIML_INITIAL_CONTEXT", "Eliza: hi"),
+      ]),
+    )
+    .unwrap();
+
+    assert_eq!(rate_limit::Config::default(), config.rate_limit);
+  }
+
+  #[test]
+  fn describes_what_changed() {
+    let old = Config::from_sources(CONFIG, env(&[])).unwrap();
+
+    let mut new = old.clone();
+    new.discord.token = String::from("new token");
+    new.chatbot.initial_context = String::from("Eliza: hello");
+    new.rate_limit.user.capacity = 10;
+    new.redis.port = 1234;
+
+    assert_eq!(
+      vec![
+        "chatbot.initial_context: \"Eliza: hi\" -> \"Eliza: hello\"",
+        "discord.token changed (takes effect after a restart)",
+        "rate_limit.user.capacity: 2 -> 10",
+        "redis.port: 6379 -> 1234 (takes effect after a restart)",
+      ],
+      changes(&old, &new)
+    );
+    assert!(changes(&old, &old).is_empty());
+  }
+
+  #[tokio::test]
+  async fn reload_applies_the_new_config() {
+    struct Subscriber(Mutex<Option<Config>>);
+
+    impl Reloadable for Subscriber {
+      fn reload(&self, config: &Config) {
+        *self.0.lock().unwrap() = Some(config.clone());
+      }
+    }
+
+    let path =
+      std::env::temp_dir().join(format!("urubu_do_pix_config_{}.toml", std::process::id()));
+    std::fs::write(&path, CONFIG).unwrap();
+
+    let subscriber = Arc::new(Subscriber(Mutex::new(None)));
+    let reloader = ConfigReloader::new(
+      path.clone(),
+      Config::from_sources(CONFIG, env(&[])).unwrap(),
+      vec![Arc::clone(&subscriber) as Arc<dyn Reloadable>],
+    )
+    .await;
+
+    std::fs::write(&path, CONFIG.replace("capacity = 2", "capacity = 3")).unwrap();
+    let result = reloader.reload().await;
+
+    std::fs::write(&path, "[discord]\ntoken = 1").unwrap();
+    let invalid = reloader.reload().await;
+
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+      Ok(vec!["rate_limit.user.capacity: 2 -> 3".to_owned()]),
+      result
+    );
+    assert_eq!(
+      Some(3),
+      subscriber
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|config| config.rate_limit.user.capacity)
+    );
+    // The current config is kept when the new one is not valid.
+    assert!(matches!(invalid, Err(ConfigError::Parse(_))));
+    assert_eq!(3, reloader.current.lock().unwrap().rate_limit.user.capacity);
+  }
+}
@@ -1,12 +1,17 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use serenity::model::prelude::Message;
+use serenity::model::prelude::{ChannelId, GuildId};
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait Browser: Send + Sync {
   /// Opens the browser and screen shares a video.
-  async fn play_video_on_discord(&self, msg: &Message, url: &str) -> Result<()>;
+  async fn play_video_on_discord(
+    &self,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    url: &str,
+  ) -> Result<()>;
 
   /// Returns true when a video is being played.
   async fn is_video_playing(&self) -> Result<bool>;
@@ -6,5 +6,33 @@ use std::time::Duration;
 #[async_trait]
 pub trait Cache: Send + Sync {
   async fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>>;
-  async fn put(&self, key: Vec<u8>, value: Vec<u8>, ttl: Duration) -> Result<()>;
+  /// Stores `value` under `key`, the value never expires when `ttl` is None.
+  async fn put(&self, key: Vec<u8>, value: Vec<u8>, ttl: Option<Duration>) -> Result<()>;
+  /// Deletes the value under `key`, if there is one.
+  async fn delete(&self, key: &[u8]) -> Result<()>;
+}
+
+/// A cache that keeps the values in memory, the values are shared with `values`
+/// so tests can look at what was stored.
+#[cfg(test)]
+pub fn in_memory(
+  values: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<Vec<u8>, Vec<u8>>>>,
+) -> MockCache {
+  let mut cache = MockCache::new();
+
+  let stored = std::sync::Arc::clone(&values);
+  cache
+    .expect_get()
+    .returning(move |key| Ok(stored.lock().unwrap().get(key).cloned()));
+  let stored = std::sync::Arc::clone(&values);
+  cache.expect_put().returning(move |key, value, _| {
+    stored.lock().unwrap().insert(key, value);
+    Ok(())
+  });
+  cache.expect_delete().returning(move |key| {
+    values.lock().unwrap().remove(key);
+    Ok(())
+  });
+
+  cache
 }
@@ -22,6 +22,7 @@ pub struct GetOptions {
   pub timeout: Option<Duration>,
 }
 
+/// Only successful responses are returned, an error status is returned as an error.
 #[derive(Debug)]
 pub struct GetResponse {
   pub headers: HeaderMap,
@@ -1,4 +1,6 @@
 pub mod browser;
 pub mod cache;
 pub mod http;
+pub mod text_generation;
+pub mod translation;
 pub mod tts;
@@ -0,0 +1,11 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::{conversation::Conversation, text_generation::Profile};
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait TextGeneration: Send + Sync {
+  /// Generates the next message of the bot in the conversation.
+  async fn generate(&self, conversation: &Conversation, profile: &Profile) -> Result<String>;
+}
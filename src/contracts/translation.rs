@@ -0,0 +1,9 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait Translator: Send + Sync {
+  /// Translates `text` between languages given as ISO 639-1 codes, like `pt` and `en`.
+  async fn translate(&self, text: &str, from_lang: &str, to_lang: &str) -> Result<String>;
+}
@@ -1,8 +1,63 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use async_trait::async_trait;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+  Mp3,
+  #[default]
+  Wav,
+  Ogg,
+}
+
+impl Codec {
+  /// The file extension ffmpeg uses to guess the format.
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Codec::Mp3 => "mp3",
+      Codec::Wav => "wav",
+      Codec::Ogg => "ogg",
+    }
+  }
+}
+
+/// Where the audio made by a [TextToSpeech] can be played from.
+#[derive(Clone, PartialEq, Eq)]
+pub enum AudioSource {
+  /// Audio hosted by the engine, it is downloaded when played.
+  Url(String),
+  /// Audio file in the disk.
+  Path(PathBuf),
+  /// Audio in memory.
+  Bytes { data: Bytes, codec: Codec },
+}
+
+impl std::fmt::Debug for AudioSource {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      AudioSource::Url(url) => f.debug_tuple("Url").field(url).finish(),
+      AudioSource::Path(path) => f.debug_tuple("Path").field(path).finish(),
+      // The audio is too long to be logged.
+      AudioSource::Bytes { data, codec } => f
+        .debug_struct("Bytes")
+        .field("len", &data.len())
+        .field("codec", codec)
+        .finish(),
+    }
+  }
+}
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait]
 pub trait TextToSpeech: Send + Sync {
-  async fn create_audio(&self, text: String) -> Result<Vec<String>>;
+  /// Reads `text` with `voice`, or with the configured voice when it is None.
+  /// Long texts may be read in more than one audio.
+  async fn create_audio(&self, text: String, voice: Option<String>) -> Result<Vec<AudioSource>>;
+
+  /// Returns the voices text can be read with.
+  async fn voices(&self) -> Result<Vec<String>>;
 }
@@ -0,0 +1,405 @@
+//! The conversation between a user and the chatbot, split into turns so old turns
+//! can be summarized or dropped without losing the persona context.
+//! Conversations are stored as JSON.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serenity::model::{id::MessageId, Timestamp};
+
+use crate::text_generation::USER_NAME;
+
+/// The speaker name used to generate the summary of the old turns.
+pub const SUMMARY_SPEAKER: &str = "Summary";
+
+/// Starts the line that holds the summary of the turns that were removed from the conversation.
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// How many tokens the conversation sent to the text generator can have.
+  pub max_tokens: usize,
+  /// How many of the latest turns are never summarized.
+  pub keep_turns: usize,
+  /// Summarize the old turns instead of dropping them.
+  pub summarize: bool,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      max_tokens: 700,
+      keep_turns: 6,
+      summarize: true,
+    }
+  }
+}
+
+/// Who the chatbot keeps a conversation with in a text channel.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+  /// Every user has their own conversation with the chatbot.
+  #[default]
+  User,
+  /// Everyone in the channel shares one conversation, user messages are labelled by display name.
+  Channel,
+}
+
+impl FromStr for Mode {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "user" => Ok(Mode::User),
+      "channel" => Ok(Mode::Channel),
+      _ => Err(anyhow::anyhow!("unknown conversation mode {}", s)),
+    }
+  }
+}
+
+impl fmt::Display for Mode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Mode::User => "user",
+      Mode::Channel => "channel",
+    })
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Speaker {
+  User,
+  Bot,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turn {
+  pub speaker: Speaker,
+  /// The message in English, the language the text generator is used in.
+  pub text: String,
+  /// The language the message was sent in.
+  pub language: String,
+  pub timestamp: Timestamp,
+  /// The discord message, unknown for turns added with the sethistory command.
+  pub message_id: Option<MessageId>,
+  /// The display name of the user who sent the message in a channel conversation.
+  #[serde(default)]
+  pub author: Option<String>,
+}
+
+impl Turn {
+  /// A turn sent in English now.
+  pub fn new(speaker: Speaker, text: &str) -> Self {
+    Self {
+      speaker,
+      text: text.to_owned(),
+      language: String::from("en"),
+      timestamp: Timestamp::now(),
+      message_id: None,
+      author: None,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conversation {
+  /// The persona context, it is always sent to the text generator.
+  pub preamble: String,
+  /// Summary of the turns that were removed to keep the conversation short.
+  pub summary: Option<String>,
+  pub turns: Vec<Turn>,
+  bot_name: String,
+}
+
+impl Conversation {
+  pub fn new(preamble: &str, bot_name: &str) -> Self {
+    Self {
+      preamble: preamble.to_owned(),
+      summary: None,
+      turns: Vec::new(),
+      bot_name: bot_name.to_owned(),
+    }
+  }
+
+  /// Parses a conversation like `Me: hi\nEliza: hello` that starts with `preamble`
+  /// or directly with the turns. The conversation gets `preamble` either way, other text
+  /// before the first turn, like the context of a conversation created before the persona
+  /// context was edited or cut in the middle when it got too long, is dropped.
+  /// Parsed conversations are stored as JSON, which keeps the preamble apart from the turns.
+  pub fn parse(text: &str, preamble: &str, bot_name: &str) -> Self {
+    let mut conversation = Self::new(preamble, bot_name);
+
+    let rest = match text.strip_prefix(preamble) {
+      Some(rest) if !preamble.is_empty() => rest,
+      _ => text,
+    };
+
+    let user_prefix = format!("{}:", USER_NAME);
+    let bot_prefix = format!("{}:", bot_name);
+
+    for line in rest.lines() {
+      if let Some(text) = line.strip_prefix(&user_prefix) {
+        conversation.push(Turn::new(Speaker::User, text.trim()));
+      } else if let Some(text) = line.strip_prefix(&bot_prefix) {
+        conversation.push(Turn::new(Speaker::Bot, text.trim()));
+      } else if let Some(turn) = conversation.turns.last_mut() {
+        turn.text.push('\n');
+        turn.text.push_str(line);
+      } else if let Some(summary) = line.strip_prefix(SUMMARY_PREFIX) {
+        conversation.summary = Some(summary.trim().to_owned());
+      }
+    }
+
+    conversation
+  }
+
+  pub fn push(&mut self, turn: Turn) {
+    self.turns.push(turn);
+  }
+
+  pub fn bot_name(&self) -> &str {
+    &self.bot_name
+  }
+
+  pub fn speaker_name<'a>(&'a self, turn: &'a Turn) -> &'a str {
+    match turn.speaker {
+      Speaker::User => turn.author.as_deref().unwrap_or(USER_NAME),
+      Speaker::Bot => &self.bot_name,
+    }
+  }
+
+  /// Returns the names of the users who sent the turns, [USER_NAME] when there are none.
+  pub fn user_names<'a>(&'a self, turns: &'a [Turn]) -> Vec<&'a str> {
+    let mut names = Vec::new();
+
+    for turn in turns.iter().filter(|turn| turn.speaker == Speaker::User) {
+      let name = self.speaker_name(turn);
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+
+    if names.is_empty() {
+      names.push(USER_NAME);
+    }
+
+    names
+  }
+
+  /// Returns the preamble and the summary, the part of the conversation before the turns.
+  pub fn context(&self) -> String {
+    let mut context = self.preamble.clone();
+    if !context.is_empty() && !context.ends_with('\n') {
+      context.push('\n');
+    }
+
+    if let Some(summary) = &self.summary {
+      context.push_str(&format!("{} {}\n", SUMMARY_PREFIX, summary));
+    }
+
+    context
+  }
+
+  pub fn tokens(&self) -> usize {
+    estimate_tokens(&self.to_string())
+  }
+
+  /// Removes and returns every turn except the latest `keep` turns.
+  pub fn take_old_turns(&mut self, keep: usize) -> Vec<Turn> {
+    let old = self.turns.len().saturating_sub(keep);
+    self.turns.drain(..old).collect()
+  }
+
+  /// Drops the oldest turns, and then the summary, until the conversation fits in `max_tokens`.
+  /// The preamble and the latest turn are always kept.
+  pub fn drop_oldest_turns(&mut self, max_tokens: usize) {
+    while self.tokens() > max_tokens && self.turns.len() > 1 {
+      self.turns.remove(0);
+    }
+
+    if self.tokens() > max_tokens {
+      self.summary = None;
+    }
+  }
+
+  /// Returns the text sent to the text generator to summarize `turns`.
+  pub fn summary_prompt(&self, turns: &[Turn]) -> String {
+    let mut prompt = format!(
+      "Write a short summary of this conversation between {} and {}.\n",
+      self.user_names(turns).join(", "),
+      self.bot_name
+    );
+
+    if let Some(summary) = &self.summary {
+      prompt.push_str(&format!("Earlier: {}\n", summary));
+    }
+
+    for turn in turns {
+      prompt.push_str(&format!(
+        "{} said: {}\n",
+        self.speaker_name(turn),
+        turn.text
+      ));
+    }
+
+    prompt
+  }
+}
+
+impl fmt::Display for Conversation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.context())?;
+
+    for turn in self.turns.iter() {
+      writeln!(f, "{}: {}", self.speaker_name(turn), turn.text)?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Estimates how many tokens the text generators split the text into.
+/// English text has about 4 characters per token.
+pub fn estimate_tokens(text: &str) -> usize {
+  text.chars().count().div_ceil(4)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PREAMBLE: &str = "Eliza is a friendly bot.\nMe: who are you?\nEliza: I am Eliza.\n";
+
+  #[test]
+  fn parses_and_renders_the_conversation() {
+    let text = format!(
+      "{}{} coração partido\nMe: olá\nEliza: tudo bem?\ne você?\n",
+      PREAMBLE, SUMMARY_PREFIX
+    );
+
+    let conversation = Conversation::parse(&text, PREAMBLE, "Eliza");
+
+    assert_eq!(PREAMBLE, conversation.preamble);
+    assert_eq!(Some("coração partido"), conversation.summary.as_deref());
+    assert_eq!(
+      vec![(Speaker::User, "olá"), (Speaker::Bot, "tudo bem?\ne você?")],
+      conversation
+        .turns
+        .iter()
+        .map(|turn| (turn.speaker, turn.text.as_str()))
+        .collect::<Vec<_>>()
+    );
+    assert_eq!(text, conversation.to_string());
+  }
+
+  #[test]
+  fn replaces_an_unknown_preamble_with_the_persona_context() {
+    // Conversations used to be stored as text cut to the last 2750 characters,
+    // so they start in the middle of a context that may have been edited since.
+    let text = concat!(
+      "ally and curious, she answers in short sentences.\n",
+      "Me: who are you?\n",
+      "Eliza: I am Eliza.\n",
+      "Me: Do you like music?\n",
+      "Eliza: Yes, I like samba.\n",
+      "Which one do you like?\n",
+      "Me: I like rock\n",
+      "Eliza: Rock is great too!\n",
+    );
+
+    let conversation = Conversation::parse(text, "Eliza is a bot.\n", "Eliza");
+
+    assert_eq!("Eliza is a bot.\n", conversation.preamble);
+    assert_eq!(
+      vec![
+        (Speaker::User, "who are you?"),
+        (Speaker::Bot, "I am Eliza."),
+        (Speaker::User, "Do you like music?"),
+        (Speaker::Bot, "Yes, I like samba.\nWhich one do you like?"),
+        (Speaker::User, "I like rock"),
+        (Speaker::Bot, "Rock is great too!"),
+      ],
+      conversation
+        .turns
+        .iter()
+        .map(|turn| (turn.speaker, turn.text.as_str()))
+        .collect::<Vec<_>>()
+    );
+
+    // Conversations without a preamble start with the persona context.
+    let conversation = Conversation::parse("Me: olá\nEliza: oi\n", PREAMBLE, "Eliza");
+
+    assert_eq!(PREAMBLE, conversation.preamble);
+    assert_eq!(2, conversation.turns.len());
+  }
+
+  #[test]
+  fn is_stored_as_json() {
+    let mut conversation = Conversation::new(PREAMBLE, "Eliza");
+    conversation.push(Turn {
+      language: "pt".to_owned(),
+      message_id: Some(MessageId(1)),
+      ..Turn::new(Speaker::User, "hi")
+    });
+
+    let json = serde_json::to_vec(&conversation).unwrap();
+
+    assert_eq!(conversation, serde_json::from_slice(&json).unwrap());
+  }
+
+  #[test]
+  fn keeps_the_preamble_when_dropping_turns() {
+    let mut conversation = Conversation::new(PREAMBLE, "Eliza");
+    for i in 0..20 {
+      conversation.push(Turn::new(Speaker::User, &format!("ãããã {}", i)));
+    }
+    let max_tokens = estimate_tokens(PREAMBLE) + 10;
+
+    conversation.drop_oldest_turns(max_tokens);
+
+    assert!(conversation.tokens() <= max_tokens);
+    assert_eq!(PREAMBLE, conversation.preamble);
+    assert_eq!("ãããã 19", conversation.turns.last().unwrap().text);
+  }
+
+  #[test]
+  fn labels_channel_turns_by_author() {
+    let mut conversation = Conversation::new(PREAMBLE, "Eliza");
+    for (author, text) in [("Ana", "hi"), ("Bob", "hey"), ("Ana", "bye")] {
+      conversation.push(Turn {
+        author: Some(author.to_owned()),
+        ..Turn::new(Speaker::User, text)
+      });
+    }
+    conversation.push(Turn::new(Speaker::Bot, "hello"));
+
+    assert_eq!(
+      format!("{}Ana: hi\nBob: hey\nAna: bye\nEliza: hello\n", PREAMBLE),
+      conversation.to_string()
+    );
+    assert_eq!(
+      vec!["Ana", "Bob"],
+      conversation.user_names(&conversation.turns)
+    );
+  }
+
+  #[test]
+  fn takes_the_old_turns() {
+    let mut conversation = Conversation::new(PREAMBLE, "Eliza");
+    conversation.push(Turn::new(Speaker::User, "1"));
+    conversation.push(Turn::new(Speaker::Bot, "2"));
+    conversation.push(Turn::new(Speaker::User, "3"));
+
+    let old = conversation.take_old_turns(1);
+
+    assert_eq!(2, old.len());
+    assert_eq!("3", conversation.turns[0].text);
+    assert_eq!(
+      "Write a short summary of this conversation between Me and Eliza.\nMe said: 1\nEliza said: 2\n",
+      conversation.summary_prompt(&old)
+    );
+  }
+}
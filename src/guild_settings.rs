@@ -0,0 +1,155 @@
+use std::{
+  collections::{BTreeMap, HashMap},
+  sync::Arc,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{GuildId, RoleId};
+use tokio::sync::RwLock;
+
+use crate::{commands, contracts, text_generation};
+
+/// Settings that can be changed by each guild at runtime.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GuildSettings {
+  /// The prefix used by the guild instead of [crate::commands::PREFIX].
+  #[serde(default)]
+  pub prefix: Option<String>,
+  /// Members with one of these roles can use commands that require the DJ permission level.
+  #[serde(default)]
+  pub dj_roles: Vec<RoleId>,
+  /// Members with one of these roles can use commands that require the admin permission level.
+  #[serde(default)]
+  pub admin_roles: Vec<RoleId>,
+  /// Sampling parameters that replace the ones in the text generation profile.
+  #[serde(default)]
+  pub generation: BTreeMap<text_generation::Parameter, f32>,
+  /// The languages the chatbot answers are shown in, the default languages when empty.
+  #[serde(default)]
+  pub answer_languages: Vec<String>,
+  /// The text to speech voice used in the guild, the configured voice when None.
+  #[serde(default)]
+  pub tts_voice: Option<String>,
+  /// Should the chatbot stop reading its replies in the voice channel?
+  #[serde(default)]
+  pub voice_chat_disabled: bool,
+}
+
+/// Persists guild settings in the cache and keeps them in memory because
+/// they are read for every message the bot receives.
+pub struct GuildSettingsStore {
+  cache: Arc<dyn contracts::cache::Cache>,
+  settings: RwLock<HashMap<GuildId, GuildSettings>>,
+}
+
+impl GuildSettingsStore {
+  pub fn new(cache: Arc<dyn contracts::cache::Cache>) -> Self {
+    Self {
+      cache,
+      settings: RwLock::new(HashMap::new()),
+    }
+  }
+
+  /// Returns the guild settings or the default settings if the guild has never changed them.
+  #[tracing::instrument(name = "GuildSettingsStore::get", skip_all, fields(guild_id = %guild_id))]
+  pub async fn get(&self, guild_id: GuildId) -> Result<GuildSettings> {
+    if let Some(settings) = self.settings.read().await.get(&guild_id) {
+      return Ok(settings.clone());
+    }
+
+    let settings = match self.cache.get(&cache_key(guild_id)).await? {
+      None => GuildSettings::default(),
+      Some(bytes) => serde_json::from_slice(&bytes)?,
+    };
+
+    self
+      .settings
+      .write()
+      .await
+      .insert(guild_id, settings.clone());
+
+    Ok(settings)
+  }
+
+  /// Returns the prefix used by the guild. Direct messages always use the default prefix.
+  pub async fn prefix(&self, guild_id: Option<GuildId>) -> Result<String> {
+    let prefix = match guild_id {
+      None => None,
+      Some(guild_id) => self.get(guild_id).await?.prefix,
+    };
+
+    Ok(prefix.unwrap_or_else(|| commands::PREFIX.to_owned()))
+  }
+
+  /// Applies `f` to the guild settings and persists the result.
+  #[tracing::instrument(name = "GuildSettingsStore::update", skip_all, fields(guild_id = %guild_id))]
+  pub async fn update<F>(&self, guild_id: GuildId, f: F) -> Result<GuildSettings>
+  where
+    F: FnOnce(&mut GuildSettings) + Send,
+  {
+    let mut settings = self.get(guild_id).await?;
+
+    f(&mut settings);
+
+    self
+      .cache
+      .put(cache_key(guild_id), serde_json::to_vec(&settings)?, None)
+      .await?;
+
+    self
+      .settings
+      .write()
+      .await
+      .insert(guild_id, settings.clone());
+
+    Ok(settings)
+  }
+}
+
+fn cache_key(guild_id: GuildId) -> Vec<u8> {
+  format!("guild_settings:{}", guild_id).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+  use mockall::predicate::eq;
+
+  use super::*;
+  use crate::contracts::cache::MockCache;
+
+  #[tokio::test]
+  async fn update_persists_settings() -> Result<()> {
+    let guild_id = GuildId(1);
+
+    let expected = GuildSettings {
+      prefix: Some("!".to_owned()),
+      ..Default::default()
+    };
+
+    let mut cache = MockCache::new();
+    cache.expect_get().times(1).returning(|_| Ok(None));
+    cache
+      .expect_put()
+      .with(
+        eq(b"guild_settings:1".to_vec()),
+        eq(serde_json::to_vec(&expected)?),
+        eq(None),
+      )
+      .times(1)
+      .returning(|_, _, _| Ok(()));
+
+    let store = GuildSettingsStore::new(Arc::new(cache));
+
+    assert_eq!(GuildSettings::default(), store.get(guild_id).await?);
+
+    store
+      .update(guild_id, |settings| settings.prefix = Some("!".to_owned()))
+      .await?;
+
+    // Read from memory, the cache is not accessed again.
+    assert_eq!(expected, store.get(guild_id).await?);
+
+    Ok(())
+  }
+}
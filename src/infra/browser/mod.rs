@@ -1,8 +1,8 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use async_trait::async_trait;
 use enigo::{Enigo, Key, KeyboardControllable};
 
-use serenity::model::prelude::{ChannelId, Message};
+use serenity::model::prelude::{ChannelId, GuildId};
 use std::time::Duration;
 use thirtyfour::{
   prelude::{ElementQueryable, ScriptRet, WebDriverResult},
@@ -14,14 +14,23 @@ use tracing::info;
 mod stremio;
 mod twitch;
 mod youtube;
-use crate::{contracts, utils::env_key};
+use crate::contracts;
 
 /// NOTE: For selenium 3.x, use "http://localhost:4444/wd/hub/session".
 const SELENIUM_ENDPOINT: &str = "http://localhost:4444";
 const WINDOW_WIDTH: i64 = 1920;
 const WINDOW_HEIGHT: i64 = 1080;
 
+#[derive(Debug, Clone)]
+pub struct Config {
+  /// Token of the user account that streams the videos.
+  pub self_bot_token: Option<String>,
+  /// The port used by the video stream api.
+  pub video_stream_api_port: u16,
+}
+
 pub struct Browser {
+  config: Config,
   inner: Mutex<Inner>,
 }
 
@@ -35,8 +44,9 @@ struct Inner {
 }
 
 impl Browser {
-  pub fn new() -> Self {
+  pub fn new(config: Config) -> Self {
     Self {
+      config,
       inner: Mutex::new(Inner {
         driver: None,
         discord_window: None,
@@ -139,22 +149,29 @@ async fn open_discord_screen_share_screen_selection(driver: &WebDriver) -> WebDr
 #[async_trait]
 impl contracts::browser::Browser for Browser {
   #[tracing::instrument(name = "Browser::play_video_on_discord", skip_all, fields(url = %url))]
-  async fn play_video_on_discord(&self, msg: &Message, url: &str) -> Result<()> {
+  async fn play_video_on_discord(
+    &self,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    url: &str,
+  ) -> Result<()> {
     let mut inner = self.init_and_get_driver().await?;
     let driver = inner.driver.clone().unwrap();
 
-    let server_url = format!(
-      "https://discord.com/channels/{}/{}",
-      msg.guild_id.expect("guild id should exist"),
-      msg.channel_id
-    );
+    let server_url = format!("https://discord.com/channels/{}/{}", guild_id, channel_id);
     if inner.discord_window.is_none() {
       info!("navigating to discord page");
       driver.goto("https://discord.com").await?;
 
       tokio::time::sleep(Duration::from_millis(200)).await;
 
-      login(&driver, env_key("DISCORD_SELF_BOT_TOKEN")?).await?;
+      let self_bot_token = self
+        .config
+        .self_bot_token
+        .clone()
+        .context("discord.self_bot_token is required to stream videos")?;
+
+      login(&driver, self_bot_token).await?;
 
       tokio::time::sleep(Duration::from_secs(3)).await;
 
@@ -166,9 +183,9 @@ impl contracts::browser::Browser for Browser {
 
       tokio::time::sleep(Duration::from_millis(200)).await;
 
-      if join_voice_channel(&driver, msg.channel_id).await.is_err() {
+      if join_voice_channel(&driver, channel_id).await.is_err() {
         tokio::time::sleep(Duration::from_secs(1)).await;
-        join_voice_channel(&driver, msg.channel_id).await?;
+        join_voice_channel(&driver, channel_id).await?;
       }
 
       inner.discord_window = Some(driver.window().await?);
@@ -184,7 +201,7 @@ impl contracts::browser::Browser for Browser {
       driver.switch_to_window(current_video_tab).await?;
 
       tokio::time::sleep(Duration::from_millis(200)).await;
-      open_video(&driver, url).await?;
+      open_video(&driver, url, self.config.video_stream_api_port).await?;
     } else {
       // It is the first video being played by the bot so there's only two tabs:
       // The discord tab and the new video tab.
@@ -194,7 +211,7 @@ impl contracts::browser::Browser for Browser {
       inner.video_tab = Some(new_video_tab.clone());
       driver.switch_to_window(new_video_tab).await?;
 
-      open_video(&driver, url).await?;
+      open_video(&driver, url, self.config.video_stream_api_port).await?;
 
       info!("screen sharing video tab number 1");
       // SAFETY: initialized above.
@@ -269,13 +286,13 @@ impl contracts::browser::Browser for Browser {
 #[tracing::instrument(name = "browser::open_video", skip_all, fields(
   url = %url
 ))]
-async fn open_video(driver: &WebDriver, url: &str) -> Result<()> {
+async fn open_video(driver: &WebDriver, url: &str, video_stream_api_port: u16) -> Result<()> {
   if is_twitch_link(url) {
     twitch::open_live(&driver, url).await?;
   } else if is_stremio_stream_link(url) {
-    stremio::open_stream_in_ffmpeg(&driver, url).await?;
+    stremio::open_stream_in_ffmpeg(&driver, url, video_stream_api_port).await?;
   } else {
-    youtube::open_video(&driver, url).await?;
+    youtube::open_video(&driver, url, video_stream_api_port).await?;
   }
 
   Ok(())
@@ -2,18 +2,20 @@ use anyhow::Result;
 use thirtyfour::WebDriver;
 use tracing::info;
 
-use crate::utils::env_key;
-
 /// Tells ffmpeg to stream the stremio video as mp4
 /// because the browser video player does not understand the .mkv format
 /// which is the format used for stremio videos.
 #[tracing::instrument(name = "stremio::open_stream_in_ffmpeg", skip_all, fields(url = %url))]
-pub async fn open_stream_in_ffmpeg(driver: &WebDriver, url: &str) -> Result<tokio::process::Child> {
+pub async fn open_stream_in_ffmpeg(
+  driver: &WebDriver,
+  url: &str,
+  video_stream_api_port: u16,
+) -> Result<tokio::process::Child> {
   kill_ffmpeg().await?;
 
   let path = format!(
     "http://localhost:{}/static/index.html?is_stremio_video=1",
-    env_key("VIDEO_STREAM_API_PORT")?
+    video_stream_api_port
   );
 
   info!("spawning ffmpeg process");
@@ -1,4 +1,3 @@
-use crate::utils::env_key;
 use anyhow::Result;
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -18,10 +17,10 @@ enum YoutubeUrlError {
 }
 
 #[tracing::instrument(name = "youtube::open_video", skip_all, fields(url = %url))]
-pub async fn open_video(driver: &WebDriver, url: &str) -> Result<()> {
+pub async fn open_video(driver: &WebDriver, url: &str, video_stream_api_port: u16) -> Result<()> {
   let path = format!(
     "http://localhost:{}/static/index.html?youtube_video_id={}",
-    env_key("VIDEO_STREAM_API_PORT")?,
+    video_stream_api_port,
     get_video_id_from_youtube_url(url)?
   );
 
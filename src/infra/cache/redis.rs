@@ -4,8 +4,10 @@ use crate::contracts;
 use anyhow::Result;
 use async_trait::async_trait;
 use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct Config {
   pub host: String,
   pub port: u16,
@@ -41,12 +43,28 @@ impl contracts::cache::Cache for RedisCache {
   }
 
   #[tracing::instrument(skip_all, fields(key = %String::from_utf8_lossy(&key)))]
-  async fn put(&self, key: Vec<u8>, value: Vec<u8>, ttl: Duration) -> Result<()> {
+  async fn put(&self, key: Vec<u8>, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
+    let mut connection = self.client.get_async_connection().await?;
+
+    match ttl {
+      None => connection.set(key, value).await?,
+      Some(ttl) => {
+        connection
+          .set_ex(key, value, ttl.as_secs() as usize)
+          .await?
+      }
+    }
+
+    Ok(())
+  }
+
+  #[tracing::instrument(skip_all, fields(key = %String::from_utf8_lossy(key)))]
+  async fn delete(&self, key: &[u8]) -> Result<()> {
     self
       .client
       .get_async_connection()
       .await?
-      .set_ex(key, value, ttl.as_secs() as usize)
+      .del::<_, ()>(key)
       .await?;
     Ok(())
   }
@@ -72,13 +90,17 @@ mod tests {
     let value = b"value".to_vec();
 
     redis
-      .put(key.clone(), value.clone(), Duration::from_secs(60))
+      .put(key.clone(), value.clone(), Some(Duration::from_secs(60)))
       .await?;
 
     let result = redis.get(&key).await?;
 
     assert_eq!(Some(value), result);
 
+    redis.delete(&key).await?;
+
+    assert_eq!(None, redis.get(&key).await?);
+
     Ok(())
   }
 }
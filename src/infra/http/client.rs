@@ -64,7 +64,7 @@ impl contracts::http::HttpClient for ReqwestHttpClient {
       }
     }
 
-    let response = request_builder.send().await?;
+    let response = request_builder.send().await?.error_for_status()?;
     let headers = response.headers().clone();
 
     Ok(GetResponse {
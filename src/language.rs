@@ -0,0 +1,125 @@
+//! Detects the language messages are written in.
+
+use whatlang::Lang;
+
+/// The language the text generators are used in, messages are translated to it.
+pub const MODEL_LANGUAGE: &str = "en";
+
+/// Returns the ISO 639-1 code of the language `text` is written in,
+/// `None` when the text is too short or ambiguous to tell.
+pub fn detect(text: &str) -> Option<&'static str> {
+  let info = whatlang::detect(text)?;
+
+  if !info.is_reliable() {
+    return None;
+  }
+
+  Some(iso_639_1(info.lang()))
+}
+
+/// Returns true if `code` looks like a language code the translators accept, like `pt` or `zh-TW`.
+pub fn is_valid_code(code: &str) -> bool {
+  (2..=8).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphabetic() || c == '-')
+}
+
+/// Returns true if `code` is one of the languages [detect] returns, like `pt` or `en`.
+pub fn is_supported(code: &str) -> bool {
+  Lang::all().iter().any(|lang| iso_639_1(*lang) == code)
+}
+
+/// The translators expect ISO 639-1 codes and whatlang uses ISO 639-3 codes.
+fn iso_639_1(lang: Lang) -> &'static str {
+  match lang {
+    Lang::Epo => "eo",
+    Lang::Eng => "en",
+    Lang::Rus => "ru",
+    Lang::Cmn => "zh",
+    Lang::Spa => "es",
+    Lang::Por => "pt",
+    Lang::Ita => "it",
+    Lang::Ben => "bn",
+    Lang::Fra => "fr",
+    Lang::Deu => "de",
+    Lang::Ukr => "uk",
+    Lang::Kat => "ka",
+    Lang::Ara => "ar",
+    Lang::Hin => "hi",
+    Lang::Jpn => "ja",
+    Lang::Heb => "he",
+    Lang::Yid => "yi",
+    Lang::Pol => "pl",
+    Lang::Amh => "am",
+    Lang::Jav => "jv",
+    Lang::Kor => "ko",
+    Lang::Nob => "no",
+    Lang::Dan => "da",
+    Lang::Swe => "sv",
+    Lang::Fin => "fi",
+    Lang::Tur => "tr",
+    Lang::Nld => "nl",
+    Lang::Hun => "hu",
+    Lang::Ces => "cs",
+    Lang::Ell => "el",
+    Lang::Bul => "bg",
+    Lang::Bel => "be",
+    Lang::Mar => "mr",
+    Lang::Kan => "kn",
+    Lang::Ron => "ro",
+    Lang::Slv => "sl",
+    Lang::Hrv => "hr",
+    Lang::Srp => "sr",
+    Lang::Mkd => "mk",
+    Lang::Lit => "lt",
+    Lang::Lav => "lv",
+    Lang::Est => "et",
+    Lang::Tam => "ta",
+    Lang::Vie => "vi",
+    Lang::Urd => "ur",
+    Lang::Tha => "th",
+    Lang::Guj => "gu",
+    Lang::Uzb => "uz",
+    Lang::Pan => "pa",
+    Lang::Aze => "az",
+    Lang::Ind => "id",
+    Lang::Tel => "te",
+    Lang::Pes => "fa",
+    Lang::Mal => "ml",
+    Lang::Ori => "or",
+    Lang::Mya => "my",
+    Lang::Nep => "ne",
+    Lang::Sin => "si",
+    Lang::Khm => "km",
+    Lang::Tuk => "tk",
+    Lang::Aka => "ak",
+    Lang::Zul => "zu",
+    Lang::Sna => "sn",
+    Lang::Afr => "af",
+    Lang::Lat => "la",
+    Lang::Slk => "sk",
+    Lang::Cat => "ca",
+    Lang::Tgl => "tl",
+    Lang::Hye => "hy",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn detects_the_language() {
+    assert_eq!(
+      Some("pt"),
+      detect("Oi, tudo bem com você? Hoje eu fui ao mercado comprar pão.")
+    );
+    assert_eq!(
+      Some("es"),
+      detect("Hola, ¿cómo estás? Hoy fui al mercado a comprar pan y leche.")
+    );
+    assert_eq!(
+      Some("en"),
+      detect("Hello, how are you doing today? I went to the store this morning to buy some bread and milk for breakfast.")
+    );
+    assert_eq!(None, detect("ok"));
+  }
+}
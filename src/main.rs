@@ -1,23 +1,37 @@
-use std::{str::SplitWhitespace, sync::Arc};
+use std::{collections::HashSet, sync::Arc};
 
 use anyhow::{anyhow, Result};
 use chatbot::ChatBot;
+use commands::{slash, AccessControl, ArgError, Args, Command, Invocation, Resolution};
 use rand::Rng;
 use serenity::async_trait;
 use serenity::client::Context;
+use serenity::http::Http;
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::autocomplete::AutocompleteInteraction;
+use serenity::model::application::interaction::Interaction;
 use serenity::model::channel::Message;
 use serenity::model::gateway::Ready;
+use serenity::model::id::UserId;
 use serenity::prelude::*;
 use songbird::SerenityInit;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 use tracing_subscriber::{filter::EnvFilter, layer::SubscriberExt, Registry};
 use tracing_tree::HierarchicalLayer;
 
 mod audio;
 mod chatbot;
+mod commands;
+mod config;
 mod contracts;
+mod conversation;
+mod guild_settings;
 mod infra;
+mod language;
+mod mentions;
+mod persona;
+mod rate_limit;
 mod text_generation;
 mod translation;
 mod tts;
@@ -27,26 +41,107 @@ mod video_stream_api;
 
 use text_generation::TextGenerator;
 use translation::Translation;
-use tts::Tts;
+use tts::{voice::VoiceStore, Tts};
 use video::Video;
 
 use crate::{
-  infra::{
-    cache::{self, redis::RedisCache},
-    http::client::ReqwestHttpClient,
-  },
-  text_generation::Config,
-  utils::env_key,
+  config::{ConfigReloader, Reloadable},
+  guild_settings::GuildSettingsStore,
+  infra::{cache::redis::RedisCache, http::client::ReqwestHttpClient},
+  rate_limit::RateLimiter,
+  utils::check_message,
 };
 
 struct Bot {
-  chatbot: ChatBot,
-  video: Arc<Video>,
+  chatbot: Arc<ChatBot>,
+  commands: commands::Registry,
+  settings: Arc<GuildSettingsStore>,
+  access_control: Arc<AccessControl>,
+  rate_limiter: Arc<RateLimiter>,
 }
 
 impl Bot {
-  pub fn new(chatbot: ChatBot, video: Arc<Video>) -> Self {
-    Self { chatbot, video }
+  pub fn new(
+    chatbot: Arc<ChatBot>,
+    commands: commands::Registry,
+    settings: Arc<GuildSettingsStore>,
+    access_control: Arc<AccessControl>,
+    rate_limiter: Arc<RateLimiter>,
+  ) -> Self {
+    Self {
+      chatbot,
+      commands,
+      settings,
+      access_control,
+      rate_limiter,
+    }
+  }
+
+  /// Executes the command if the user that invoked it is allowed to and is not being rate limited.
+  #[tracing::instrument(skip_all, fields(command = %command.name()))]
+  async fn execute_command(
+    &self,
+    ctx: &Context,
+    command: &dyn Command,
+    invocation: &Invocation,
+    args: Args,
+    prefix: &str,
+  ) -> Result<()> {
+    if let Err(throttled) = self.rate_limiter.check_command(
+      invocation.author().id,
+      invocation.channel_id(),
+      command.name(),
+    ) {
+      info!("user is being rate limited. throttled={:?}", throttled);
+      if throttled.should_warn {
+        invocation
+          .reply(ctx, rate_limit::wait_message(throttled.wait_time))
+          .await?;
+      }
+      return Ok(());
+    }
+
+    if let Err(required) = self
+      .access_control
+      .check(ctx, invocation, command, &args)
+      .await?
+    {
+      info!("permission denied. required={}", required);
+      invocation
+        .reply(
+          ctx,
+          format!(
+            "you need the {} permission level to use this command",
+            required
+          ),
+        )
+        .await?;
+      return Ok(());
+    }
+
+    let subcommand = args.peek().map(String::from);
+
+    match command.execute(ctx, invocation, args).await {
+      Err(err) => match err.downcast_ref::<ArgError>() {
+        None => Err(err),
+        Some(err) => {
+          info!("invalid arguments. error={}", err);
+          let usage = if prefix == "/" {
+            slash::usage(command, subcommand.as_deref())
+          } else {
+            format!(
+              "{}{}",
+              prefix,
+              commands::usage(command, subcommand.as_deref())
+            )
+          };
+          invocation
+            .reply(ctx, format!("{}\nusage: `{}`", err, usage))
+            .await
+        }
+      },
+      Ok(()) => Ok(()),
+    }
   }
 
   #[tracing::instrument(skip_all, fields(
@@ -63,113 +158,169 @@ impl Bot {
       return;
     }
 
-    let prefix = "b!";
-    let is_command = msg.content.starts_with(prefix);
+    let prefix = match self.settings.prefix(msg.guild_id).await {
+      Err(err) => {
+        error!("error getting guild prefix. error={:?}", err);
+        commands::PREFIX.to_owned()
+      }
+      Ok(prefix) => prefix,
+    };
 
-    if !is_command {
-      if let Err(err) = sex(&ctx, msg).await {
-        error!("error executing sexo handler. error={:?}", err);
+    let input = commands::strip_prefix(&msg.content, &prefix, ctx.cache.current_user_id());
+
+    let input = match input {
+      Some(input) => input,
+      None => {
+        if let Err(err) = sex(&ctx, msg).await {
+          error!("error executing sexo handler. error={:?}", err);
+        }
+
+        if let Err(err) = self.chatbot.on_message(&ctx, msg).await {
+          error!("error executing conversation_bot handler. error={:?}", err);
+        }
+
+        return;
       }
+    };
 
-      if let Err(err) = self.chatbot.on_message(&ctx, msg).await {
-        error!("error executing conversation_bot handler. error={:?}", err);
+    match self.commands.resolve(input) {
+      Resolution::Empty => {
+        info!("Ta maluco porra");
       }
+      Resolution::Unknown(cmd) => {
+        info!("unknown command. command={}", cmd);
 
-      return;
+        if let Some(suggestion) = self.commands.suggest(cmd) {
+          check_message(
+            msg
+              .reply(
+                &ctx,
+                format!(
+                  "unknown command `{}`. Did you mean `{}{}`? Use `{}help` to see every command.",
+                  cmd, prefix, suggestion, prefix
+                ),
+              )
+              .await,
+          );
+        }
+      }
+      Resolution::Found { command, args } => {
+        let invocation = Invocation::from(msg.clone());
+
+        if let Err(err) = self
+          .execute_command(&ctx, command.as_ref(), &invocation, args, &prefix)
+          .await
+        {
+          error!(
+            "error executing command. command={} error={:?}",
+            command.name(),
+            err
+          );
+        }
+      }
     }
+  }
 
-    let mut args = msg.content[prefix.len()..].split_whitespace();
-
-    let cmd = match args.next() {
+  #[tracing::instrument(skip_all, fields(
+    author_id = %interaction.user.id,
+    author_name = %interaction.user.name,
+    command = %interaction.data.name
+  ))]
+  async fn application_command_handler(
+    &self,
+    ctx: Context,
+    interaction: ApplicationCommandInteraction,
+  ) {
+    let command = match self.commands.find(&interaction.data.name) {
       None => {
-        info!("Ta maluco porra");
+        info!("unknown slash command");
         return;
       }
-      Some(v) => v,
+      Some(command) => command,
     };
 
-    let result = match cmd {
-      "echo" => echo(&ctx, msg, args.collect::<Vec<_>>().join(" ")).await,
-      "zanders" => zanders(&ctx, msg).await,
-      "sound" => audio::handler(&ctx, msg, args.collect::<Vec<_>>()).await,
-      "chatbot" => self.chatbot(&ctx, msg, args).await,
-      "video" => match args.next() {
-        None => Err(anyhow!("video url is required")),
-        Some(url) => self.video.play(&ctx, msg, url).await,
-      },
-      "videoskip" => self.video.skip_current_video(&ctx, msg).await,
-      cmd => {
-        info!("unknown command. command={}", cmd);
-        Ok(())
+    let args = slash::args_from_options(command.as_ref(), &interaction.data.options);
+
+    let invocation = Invocation::from(interaction);
+
+    if let Err(err) = invocation.acknowledge(&ctx).await {
+      error!("error acknowledging slash command. error={:?}", err);
+      return;
+    }
+
+    if let Err(err) = self
+      .execute_command(&ctx, command.as_ref(), &invocation, args, "/")
+      .await
+    {
+      error!(
+        "error executing command. command={} error={:?}",
+        command.name(),
+        err
+      );
+
+      if let Err(err) = invocation
+        .reply(&ctx, "something went wrong executing the command")
+        .await
+      {
+        error!("error replying to slash command. error={:?}", err);
       }
-    };
+    }
 
-    if let Err(err) = result {
-      error!("error executing command. command={} error={:?}", cmd, err);
+    if let Err(err) = invocation.finish(&ctx).await {
+      error!("error finishing slash command. error={:?}", err);
     }
   }
 
-  #[tracing::instrument(name = "chatbot", skip_all)]
-  async fn chatbot(
-    &self,
-    ctx: &Context,
-    msg: &Message,
-    mut args: SplitWhitespace<'_>,
-  ) -> Result<()> {
-    match args.next() {
-      None => self.chatbot.join_text_channel(ctx, msg).await?,
-      Some(subcommand) => match subcommand {
-        "eliza" => {
-          self
-            .chatbot
-            .set_user_history(msg.author.id.0, &env_key("CHAIML_INITIAL_CONTEXT")?)
-            .await?;
-          msg.reply(&ctx, "history set").await?;
-        }
-        "sethistory" => {
-          self
-            .chatbot
-            .set_user_history(msg.author.id.0, &msg.content)
-            .await?;
-          msg.reply(&ctx, "history set").await?;
-        }
-        "history" => {
-          msg
-            .reply(
-              &ctx,
-              self
-                .chatbot
-                .conversation_history_for_user(msg.author.id.0)
-                .await?,
-            )
-            .await?;
-        }
-        "voice" => {
-          let arg = args.next();
-          match arg {
-            Some("enable") => {
-              self.chatbot.enable_voice();
-              msg.reply(&ctx, "voice chat enabled").await?;
-            }
-            Some("disable") => {
-              self.chatbot.disable_voice();
-              msg.reply(&ctx, "voice chat disabled").await?;
-            }
-            _ => {
-              msg
-                .reply(&ctx, format!("unexpected argument: {:?}", arg))
-                .await?;
-            }
-          }
-        }
-        _ => {
-          info!("unknown subcommand. subcommand={}", subcommand);
+  #[tracing::instrument(skip_all, fields(command = %interaction.data.name))]
+  async fn autocomplete_handler(&self, ctx: Context, interaction: AutocompleteInteraction) {
+    let command = match self.commands.find(&interaction.data.name) {
+      None => return,
+      Some(command) => command,
+    };
+
+    let (option, value) = match slash::focused_option(&interaction.data.options) {
+      None => return,
+      Some(focused) => focused,
+    };
+
+    let suggestions = match command.autocomplete(option, value).await {
+      Err(err) => {
+        error!("error getting autocomplete suggestions. error={:?}", err);
+        return;
+      }
+      Ok(suggestions) => suggestions,
+    };
+
+    // Discord accepts at most 25 suggestions.
+    const MAX_SUGGESTIONS: usize = 25;
+
+    let result = interaction
+      .create_autocomplete_response(&ctx.http, |response| {
+        for suggestion in suggestions.iter().take(MAX_SUGGESTIONS) {
+          response.add_string_choice(suggestion, suggestion);
         }
-      },
+        response
+      })
+      .await;
+
+    if let Err(err) = result {
+      error!("error sending autocomplete suggestions. error={:?}", err);
     }
+  }
+}
+
+/// Returns the users that own the bot application, they have every permission level.
+#[tracing::instrument(skip_all)]
+async fn bot_owners(token: &str) -> Result<HashSet<UserId>> {
+  let application_info = Http::new(token).get_current_application_info().await?;
 
-    Ok(())
+  let mut owners = HashSet::from([application_info.owner.id]);
+
+  if let Some(team) = application_info.team {
+    owners.extend(team.members.into_iter().map(|member| member.user.id));
   }
+
+  Ok(owners)
 }
 
 #[tracing::instrument(name = "sexo", skip_all)]
@@ -183,34 +334,39 @@ async fn sex(ctx: &Context, msg: &Message) -> Result<()> {
   Ok(())
 }
 
-#[tracing::instrument(name = "echo", skip_all)]
-async fn echo(ctx: &Context, msg: &Message, arg: String) -> Result<()> {
-  msg.reply(ctx, arg).await?;
-
-  Ok(())
-}
-
-#[tracing::instrument(name = "zanders", skip_all)]
-async fn zanders(ctx: &Context, msg: &Message) -> Result<()> {
-  audio::play_local_audio(ctx, msg, "yo_zanders.mp3").await?;
-
-  Ok(())
-}
-
 #[async_trait]
 impl EventHandler for Bot {
-  async fn ready(&self, _: Context, ready: Ready) {
-    info!("Bot is ready as {}", ready.user.name)
+  async fn ready(&self, ctx: Context, ready: Ready) {
+    info!("Bot is ready as {}", ready.user.name);
+
+    if let Err(err) = slash::register_commands(&ctx, &self.commands).await {
+      error!("error registering slash commands. error={:?}", err);
+    }
+
+    if let Err(err) = self.chatbot.restore_text_channels().await {
+      error!("error restoring chatbot text channels. error={:?}", err);
+    }
   }
 
   async fn message(&self, ctx: Context, msg: Message) {
     self.command_handler(ctx, &msg).await;
   }
+
+  async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+    match interaction {
+      Interaction::ApplicationCommand(interaction) => {
+        self.application_command_handler(ctx, interaction).await
+      }
+      Interaction::Autocomplete(interaction) => self.autocomplete_handler(ctx, interaction).await,
+      _ => {}
+    }
+  }
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-  dotenv::dotenv().expect("error reading .env file");
+  // Every config value can be overridden by an env var so the .env file is optional.
+  dotenv::dotenv().ok();
 
   // let (non_blocking_writer, _guard) = tracing_appender::non_blocking(std::io::stdout());
 
@@ -226,7 +382,96 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
   tracing::subscriber::set_global_default(subscriber)?;
 
-  let token = env_key("DISCORD_TOKEN")?;
+  let config_path = std::path::PathBuf::from(
+    std::env::var("CONFIG_PATH").unwrap_or_else(|_| config::DEFAULT_CONFIG_PATH.to_owned()),
+  );
+  let config = config::Config::load(&config_path).await?;
+
+  if config.discord.self_bot_token.is_none() {
+    warn!("discord.self_bot_token is not set, videos will not be streamed");
+  }
+
+  let token = config.discord.token.clone();
+
+  let cache: Arc<dyn contracts::cache::Cache> = Arc::new(RedisCache::new(config.redis.clone())?);
+
+  let settings = Arc::new(GuildSettingsStore::new(Arc::clone(&cache)));
+
+  let owners = bot_owners(&token).await?;
+  let access_control = Arc::new(AccessControl::new(Arc::clone(&settings), owners));
+
+  let rate_limiter = Arc::new(RateLimiter::new(config.rate_limit));
+
+  let tts = Arc::new(Tts::new(
+    config.tts.clone(),
+    Arc::new(ReqwestHttpClient::new()),
+  ));
+
+  let chatbot = Arc::new(ChatBot::new(
+    config.chatbot.clone(),
+    Arc::clone(&tts) as Arc<dyn contracts::tts::TextToSpeech>,
+    TextGenerator::new(
+      config.text_generation.clone(),
+      Arc::new(ReqwestHttpClient::new()),
+    ),
+    Translation::new(
+      config.translation.clone(),
+      Arc::new(ReqwestHttpClient::new()),
+      Arc::clone(&cache),
+    ),
+    Arc::clone(&cache),
+    Arc::clone(&settings),
+    Arc::clone(&rate_limiter),
+  ));
+
+  let video = Video::new(Arc::new(infra::browser::Browser::new(
+    infra::browser::Config {
+      self_bot_token: config.discord.self_bot_token.clone(),
+      video_stream_api_port: config.video_stream_api.port,
+    },
+  )));
+
+  let config_reloader = ConfigReloader::new(
+    config_path,
+    config.clone(),
+    vec![
+      Arc::clone(&chatbot) as Arc<dyn Reloadable>,
+      Arc::clone(&tts) as Arc<dyn Reloadable>,
+      Arc::clone(&rate_limiter) as Arc<dyn Reloadable>,
+    ],
+  )
+  .await;
+  config_reloader.watch();
+
+  let mut commands = commands::Registry::new();
+  commands
+    .register(Arc::new(commands::EchoCommand))?
+    .register(Arc::new(commands::ZandersCommand))?
+    .register(Arc::new(commands::SoundCommand))?
+    .register(Arc::new(commands::ChatBotCommand::new(
+      Arc::clone(&chatbot),
+      Arc::clone(&access_control),
+    )))?
+    .register(Arc::new(commands::SayCommand::new(
+      Arc::clone(&chatbot),
+      Arc::clone(&tts),
+    )))?
+    .register(Arc::new(commands::TtsCommand::new(
+      tts,
+      Arc::new(VoiceStore::new(Arc::clone(&cache), Arc::clone(&settings))),
+    )))?
+    .register(Arc::new(commands::VideoCommand::new(Arc::clone(&video))))?
+    .register(Arc::new(commands::VideoSkipCommand::new(video)))?
+    .register(Arc::new(commands::PrefixCommand::new(Arc::clone(
+      &settings,
+    ))))?
+    .register(Arc::new(commands::PermissionsCommand::new(Arc::clone(
+      &settings,
+    ))))?
+    .register(Arc::new(commands::ConfigCommand::new(config_reloader)))?;
+
+  let help = commands::HelpCommand::new(commands.clone(), Arc::clone(&settings));
+  commands.register(Arc::new(help))?;
 
   let mut client = Client::builder(
     token,
@@ -234,24 +479,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
       | GatewayIntents::MESSAGE_CONTENT
       | GatewayIntents::GUILD_VOICE_STATES,
   )
+  .application_id(config.discord.app_id)
   .event_handler(Bot::new(
-    ChatBot::new(
-      Arc::new(Tts::new()),
-      TextGenerator::new(
-        Config {
-          chaiml_developer_uuid: env_key("CHAIML_DEVELOPER_UUID")?,
-          chaiml_key: env_key("CHAIML_KEY")?,
-        },
-        Arc::new(ReqwestHttpClient::new()),
-      ),
-      Translation::new(Arc::new(ReqwestHttpClient::new())),
-      Arc::new(RedisCache::new(cache::redis::Config {
-        host: env_key("REDIS_HOST")?,
-        port: env_key("REDIS_PORT")?.parse::<u16>()?,
-        password: env_key("REDIS_PASSWORD")?,
-      })?),
-    ),
-    Video::new(Arc::new(infra::browser::Browser::new())),
+    chatbot,
+    commands,
+    settings,
+    access_control,
+    rate_limiter,
   ))
   .register_songbird()
   .await
@@ -260,7 +494,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
   info!("starting bot");
 
   let result: Result<(), anyhow::Error> = tokio::select! {
-    err = axum::Server::bind(&format!("0.0.0.0:{}", env_key("VIDEO_STREAM_API_PORT")?).parse()?).serve(video_stream_api::router().into_make_service()) => Err(anyhow!("{:?}", err)),
+    err = axum::Server::bind(&format!("0.0.0.0:{}", config.video_stream_api.port).parse()?).serve(video_stream_api::router(&config.video_stream_api).into_make_service()) => Err(anyhow!("{:?}", err)),
     err = client.start() => Err(anyhow!("{:?}", err))
   };
 
@@ -0,0 +1,177 @@
+//! Discord mentions like `<@1234>` are replaced with names before the chatbot
+//! sees a message, and names in the chatbot replies are turned back into mentions.
+
+use serenity::model::id::{ChannelId, RoleId, UserId};
+
+/// Names shorter than this are not turned into mentions, they are likely to be part of other words.
+const MIN_MENTION_NAME_LEN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mention {
+  User(UserId),
+  Role(RoleId),
+  Channel(ChannelId),
+}
+
+/// Replaces the mentions in `content` with `@name` for users and roles, `#name` for
+/// channels and `:name:` for custom emoji. Mentions without a name are kept.
+pub fn resolve(content: &str, name: impl Fn(Mention) -> Option<String>) -> String {
+  let mut resolved = String::with_capacity(content.len());
+  let mut rest = content;
+
+  while let Some(start) = rest.find('<') {
+    resolved.push_str(&rest[..start]);
+    rest = &rest[start..];
+
+    let end = match rest.find('>') {
+      None => break,
+      Some(end) => end,
+    };
+
+    let token = &rest[1..end];
+
+    let replacement = if let Some(emoji) = parse_emoji(token) {
+      Some(format!(":{}:", emoji))
+    } else {
+      parse_mention(token).and_then(|mention| {
+        name(mention).map(|name| match mention {
+          Mention::User(_) | Mention::Role(_) => format!("@{}", name),
+          Mention::Channel(_) => format!("#{}", name),
+        })
+      })
+    };
+
+    match replacement {
+      Some(replacement) => {
+        resolved.push_str(&replacement);
+        rest = &rest[end + 1..];
+      }
+      None => {
+        resolved.push('<');
+        rest = &rest[1..];
+      }
+    }
+  }
+
+  resolved.push_str(rest);
+
+  resolved
+}
+
+/// Parses `@id`, `@!id`, `@&id` and `#id`, the mention without the angle brackets.
+fn parse_mention(token: &str) -> Option<Mention> {
+  if let Some(id) = token.strip_prefix("@&") {
+    return id.parse().ok().map(|id| Mention::Role(RoleId(id)));
+  }
+
+  if let Some(id) = token.strip_prefix('@') {
+    let id = id.strip_prefix('!').unwrap_or(id);
+    return id.parse().ok().map(|id| Mention::User(UserId(id)));
+  }
+
+  token
+    .strip_prefix('#')
+    .and_then(|id| id.parse().ok())
+    .map(|id| Mention::Channel(ChannelId(id)))
+}
+
+/// Returns the name of a custom emoji like `:name:id` or `a:name:id` for animated emoji.
+fn parse_emoji(token: &str) -> Option<&str> {
+  let token = token.strip_prefix('a').unwrap_or(token);
+  let (name, id) = token.strip_prefix(':')?.split_once(':')?;
+
+  if name.is_empty() || id.parse::<u64>().is_err() {
+    return None;
+  }
+
+  Some(name)
+}
+
+/// Replaces the names of `members` in `text`, as whole words and with or without `@`, with their mentions.
+pub fn mention_members(text: &str, members: &[(UserId, String)]) -> String {
+  let mut members: Vec<&(UserId, String)> = members
+    .iter()
+    .filter(|(_, name)| {
+      name.chars().count() >= MIN_MENTION_NAME_LEN && !name.chars().all(|c| c.is_ascii_digit())
+    })
+    .collect();
+
+  // Longer names first so a name that contains another one is replaced as a whole.
+  members.sort_by_key(|(_, name)| std::cmp::Reverse(name.len()));
+
+  let mut text = text.to_owned();
+
+  for (user_id, name) in members {
+    text = replace_word(&text, name, &format!("<@{}>", user_id));
+  }
+
+  text
+}
+
+fn replace_word(text: &str, word: &str, replacement: &str) -> String {
+  let is_word_char = |c: Option<char>| matches!(c, Some(c) if c.is_alphanumeric() || c == '_');
+
+  let mut replaced = String::with_capacity(text.len());
+  let mut last = 0;
+
+  for (start, _) in text.match_indices(word) {
+    let end = start + word.len();
+    if start < last
+      || is_word_char(text[..start].chars().next_back())
+      || is_word_char(text[end..].chars().next())
+    {
+      continue;
+    }
+
+    let start = if text[..start].ends_with('@') {
+      start - 1
+    } else {
+      start
+    };
+
+    replaced.push_str(&text[last..start]);
+    replaced.push_str(replacement);
+    last = end;
+  }
+
+  replaced.push_str(&text[last..]);
+
+  replaced
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn resolves_mentions_to_names() {
+    let name = |mention| match mention {
+      Mention::User(UserId(1)) => Some(String::from("Ana")),
+      Mention::Role(RoleId(2)) => Some(String::from("mods")),
+      Mention::Channel(ChannelId(3)) => Some(String::from("general")),
+      _ => None,
+    };
+
+    assert_eq!(
+      "hi @Ana and @Ana, @mods go to #general :pog: :dance: <@5> <3",
+      resolve(
+        "hi <@1> and <@!1>, <@&2> go to <#3> <:pog:4> <a:dance:6> <@5> <3",
+        name
+      )
+    );
+  }
+
+  #[test]
+  fn turns_member_names_into_mentions() {
+    let members = vec![
+      (UserId(1), String::from("Ana")),
+      (UserId(2), String::from("Ana Maria")),
+      (UserId(3), String::from("Bo")),
+    ];
+
+    assert_eq!(
+      "<@1>, <@2> and Bo are here. Banana",
+      mention_members("@Ana, Ana Maria and Bo are here. Banana", &members)
+    );
+  }
+}
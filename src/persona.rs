@@ -0,0 +1,439 @@
+//! Chatbot personas. Personas come from the config file and can be created
+//! and edited at runtime, the runtime personas are persisted in the cache.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serenity::model::id::UserId;
+use tokio::sync::RwLock;
+
+use crate::{
+  chatbot, contracts, language,
+  text_generation::{Parameter, ProfileError, USER_NAME},
+};
+
+/// The language used when the persona does not set one.
+pub const DEFAULT_LANGUAGE: &str = "pt";
+
+/// The maximum number of characters in a persona name.
+const MAX_NAME_LEN: usize = 32;
+
+const PERSONAS_CACHE_KEY: &[u8] = b"chatbot:personas";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Persona {
+  /// The name the bot messages are prefixed with in the conversation.
+  pub name: String,
+  /// The conversation every user starts with.
+  pub initial_context: String,
+  /// The text to speech voice, the guild voice is used when it is not set. The voice users choose comes first.
+  pub voice: Option<String>,
+  /// The language users are answered in when the language of their messages cannot be detected.
+  pub language: String,
+  /// Sampling parameters that replace the ones in the text generation profile.
+  pub profile: BTreeMap<Parameter, f32>,
+}
+
+impl Default for Persona {
+  fn default() -> Self {
+    Self {
+      name: String::new(),
+      initial_context: String::new(),
+      voice: None,
+      language: String::from(DEFAULT_LANGUAGE),
+      profile: BTreeMap::new(),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum PersonaError {
+  #[error("the persona name must have between 1 and {MAX_NAME_LEN} letters, numbers, - or _")]
+  InvalidName,
+  #[error("{0} is the name the users have in the conversation")]
+  ReservedName(String),
+  #[error("there is no persona called {0}")]
+  NotFound(String),
+  #[error("there is already a persona called {0}")]
+  AlreadyExists(String),
+  #[error("{0} comes from the config file and can only be removed from there")]
+  Configured(String),
+  #[error("unknown field {0}, the fields are: context, voice, language or a sampling parameter")]
+  UnknownField(String),
+  #[error("{0} is not a number")]
+  NotANumber(String),
+  #[error("{0} is not a language, use a code like pt or en")]
+  UnknownLanguage(String),
+  #[error("{0} is not a voice, use tts voices to see the voices")]
+  UnknownVoice(String),
+  #[error(transparent)]
+  Profile(#[from] ProfileError),
+}
+
+impl Persona {
+  pub fn validate_name(name: &str) -> Result<(), PersonaError> {
+    let is_valid = !name.is_empty()
+      && name.chars().count() <= MAX_NAME_LEN
+      && name
+        .chars()
+        .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+    if !is_valid {
+      return Err(PersonaError::InvalidName);
+    }
+
+    // The persona turns would be taken for the user turns.
+    if name.eq_ignore_ascii_case(USER_NAME) {
+      return Err(PersonaError::ReservedName(name.to_owned()));
+    }
+
+    Ok(())
+  }
+
+  /// Changes a field, `value` is `None` to go back to the default value.
+  pub fn set(&mut self, field: &str, value: Option<&str>) -> Result<(), PersonaError> {
+    match field {
+      "context" => self.initial_context = value.unwrap_or_default().to_owned(),
+      "voice" => self.voice = value.map(String::from),
+      "language" => {
+        let value = value.unwrap_or(DEFAULT_LANGUAGE);
+        if !language::is_supported(value) {
+          return Err(PersonaError::UnknownLanguage(value.to_owned()));
+        }
+        self.language = value.to_owned();
+      }
+      field => {
+        let parameter: Parameter = field
+          .parse()
+          .map_err(|_| PersonaError::UnknownField(field.to_owned()))?;
+
+        match value {
+          None => {
+            self.profile.remove(&parameter);
+          }
+          Some(value) => {
+            let value: f32 = value
+              .parse()
+              .map_err(|_| PersonaError::NotANumber(value.to_owned()))?;
+            parameter.validate(value)?;
+            self.profile.insert(parameter, value);
+          }
+        }
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Returns the problems with the persona, used to validate the config.
+  pub fn validate(&self) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Err(err) = Self::validate_name(&self.name) {
+      problems.push(err.to_string());
+    }
+
+    if !language::is_supported(&self.language) {
+      problems.push(PersonaError::UnknownLanguage(self.language.clone()).to_string());
+    }
+
+    for (parameter, value) in self.profile.iter() {
+      if let Err(err) = parameter.validate(*value) {
+        problems.push(err.to_string());
+      }
+    }
+
+    problems
+  }
+}
+
+/// Keeps the personas created at runtime and the persona each user has chosen.
+pub struct PersonaStore {
+  cache: Arc<dyn contracts::cache::Cache>,
+  /// Personas created or edited at runtime by name in lowercase, loaded from the cache when needed.
+  custom: RwLock<Option<BTreeMap<String, Persona>>>,
+}
+
+impl PersonaStore {
+  pub fn new(cache: Arc<dyn contracts::cache::Cache>) -> Self {
+    Self {
+      cache,
+      custom: RwLock::new(None),
+    }
+  }
+
+  async fn custom(&self) -> Result<BTreeMap<String, Persona>> {
+    if let Some(custom) = self.custom.read().await.as_ref() {
+      return Ok(custom.clone());
+    }
+
+    let custom: BTreeMap<String, Persona> = match self.cache.get(PERSONAS_CACHE_KEY).await? {
+      None => BTreeMap::new(),
+      Some(bytes) => serde_json::from_slice(&bytes)?,
+    };
+
+    *self.custom.write().await = Some(custom.clone());
+
+    Ok(custom)
+  }
+
+  async fn persist(&self, custom: BTreeMap<String, Persona>) -> Result<()> {
+    self
+      .cache
+      .put(
+        PERSONAS_CACHE_KEY.to_vec(),
+        serde_json::to_vec(&custom)?,
+        None,
+      )
+      .await?;
+
+    *self.custom.write().await = Some(custom);
+
+    Ok(())
+  }
+
+  /// Returns every persona, personas edited at runtime replace the ones in the config.
+  pub async fn list(&self, config: &chatbot::Config) -> Result<Vec<Persona>> {
+    let mut personas: BTreeMap<String, Persona> = config
+      .personas()
+      .into_iter()
+      .map(|persona| (persona.name.to_lowercase(), persona))
+      .collect();
+
+    personas.extend(self.custom().await?);
+
+    Ok(personas.into_values().collect())
+  }
+
+  /// Finds a persona by name, ignoring the case.
+  pub async fn get(&self, config: &chatbot::Config, name: &str) -> Result<Option<Persona>> {
+    let name = name.to_lowercase();
+
+    Ok(
+      self
+        .list(config)
+        .await?
+        .into_iter()
+        .find(|persona| persona.name.to_lowercase() == name),
+    )
+  }
+
+  /// Returns the persona the user has chosen or the default persona.
+  #[tracing::instrument(name = "PersonaStore::selected", skip_all, fields(user_id = %user_id))]
+  pub async fn selected(&self, config: &chatbot::Config, user_id: UserId) -> Result<Persona> {
+    if let Some(bytes) = self.cache.get(&selected_cache_key(user_id)).await? {
+      // The persona may have been deleted after the user chose it.
+      if let Some(persona) = self.get(config, &String::from_utf8_lossy(&bytes)).await? {
+        return Ok(persona);
+      }
+    }
+
+    self.default_persona(config).await
+  }
+
+  /// Returns the persona users talk to until they choose another one.
+  pub async fn default_persona(&self, config: &chatbot::Config) -> Result<Persona> {
+    let persona = self
+      .get(config, &config.default_persona)
+      .await?
+      .ok_or_else(|| PersonaError::NotFound(config.default_persona.clone()))?;
+
+    Ok(persona)
+  }
+
+  #[tracing::instrument(name = "PersonaStore::select", skip_all, fields(user_id = %user_id, name = %name))]
+  pub async fn select(
+    &self,
+    config: &chatbot::Config,
+    user_id: UserId,
+    name: &str,
+  ) -> Result<Persona> {
+    let persona = self
+      .get(config, name)
+      .await?
+      .ok_or_else(|| PersonaError::NotFound(name.to_owned()))?;
+
+    self
+      .cache
+      .put(
+        selected_cache_key(user_id),
+        persona.name.as_bytes().to_vec(),
+        None,
+      )
+      .await?;
+
+    Ok(persona)
+  }
+
+  #[tracing::instrument(name = "PersonaStore::create", skip_all, fields(name = %persona.name))]
+  pub async fn create(&self, config: &chatbot::Config, persona: Persona) -> Result<()> {
+    Persona::validate_name(&persona.name)?;
+
+    if self.get(config, &persona.name).await?.is_some() {
+      return Err(PersonaError::AlreadyExists(persona.name).into());
+    }
+
+    let mut custom = self.custom().await?;
+    custom.insert(persona.name.to_lowercase(), persona);
+    self.persist(custom).await
+  }
+
+  /// Changes a field of a persona, see [Persona::set].
+  #[tracing::instrument(name = "PersonaStore::edit", skip_all, fields(name = %name, field = %field))]
+  pub async fn edit(
+    &self,
+    config: &chatbot::Config,
+    name: &str,
+    field: &str,
+    value: Option<&str>,
+  ) -> Result<Persona> {
+    let mut persona = self
+      .get(config, name)
+      .await?
+      .ok_or_else(|| PersonaError::NotFound(name.to_owned()))?;
+
+    persona.set(field, value)?;
+
+    let mut custom = self.custom().await?;
+    custom.insert(persona.name.to_lowercase(), persona.clone());
+    self.persist(custom).await?;
+
+    Ok(persona)
+  }
+
+  /// Removes a persona created at runtime. Personas in the config that were edited
+  /// at runtime go back to the way they are in the config.
+  #[tracing::instrument(name = "PersonaStore::delete", skip_all, fields(name = %name))]
+  pub async fn delete(&self, config: &chatbot::Config, name: &str) -> Result<()> {
+    let mut custom = self.custom().await?;
+
+    if custom.remove(&name.to_lowercase()).is_none() {
+      return Err(
+        match self.get(config, name).await? {
+          None => PersonaError::NotFound(name.to_owned()),
+          Some(persona) => PersonaError::Configured(persona.name),
+        }
+        .into(),
+      );
+    }
+
+    self.persist(custom).await
+  }
+}
+
+fn selected_cache_key(user_id: UserId) -> Vec<u8> {
+  format!("chatbot:persona:{}", user_id).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::contracts::cache;
+
+  fn config() -> chatbot::Config {
+    chatbot::Config {
+      initial_context: String::from("Eliza: hi"),
+      personas: vec![Persona {
+        name: String::from("Pirate"),
+        initial_context: String::from("Pirate: arr"),
+        ..Default::default()
+      }],
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn users_switch_between_personas() -> Result<()> {
+    let config = config();
+    let store = PersonaStore::new(Arc::new(cache::in_memory(Arc::default())));
+    let user_id = UserId(1);
+
+    assert_eq!("Eliza", store.selected(&config, user_id).await?.name);
+
+    store.select(&config, user_id, "pirate").await?;
+    assert_eq!("Pirate", store.selected(&config, user_id).await?.name);
+
+    let err = store.select(&config, user_id, "ninja").await.unwrap_err();
+    assert_eq!(
+      Some(&PersonaError::NotFound(String::from("ninja"))),
+      err.downcast_ref()
+    );
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn personas_are_created_and_edited_at_runtime() -> Result<()> {
+    let config = config();
+    let store = PersonaStore::new(Arc::new(cache::in_memory(Arc::default())));
+
+    store
+      .create(
+        &config,
+        Persona {
+          name: String::from("Ninja"),
+          ..Default::default()
+        },
+      )
+      .await?;
+
+    store
+      .edit(&config, "ninja", "temperature", Some("0.5"))
+      .await?;
+    store
+      .edit(&config, "pirate", "voice", Some("en-US"))
+      .await?;
+
+    let personas = store.list(&config).await?;
+    assert_eq!(
+      vec!["Eliza", "Ninja", "Pirate"],
+      personas.iter().map(|p| p.name.as_str()).collect::<Vec<_>>()
+    );
+    assert_eq!(Some(&0.5), personas[1].profile.get(&Parameter::Temperature));
+    assert_eq!(Some("en-US"), personas[2].voice.as_deref());
+
+    let err = store
+      .edit(&config, "ninja", "temperature", Some("5"))
+      .await
+      .unwrap_err();
+    assert!(err.downcast_ref::<PersonaError>().is_some());
+
+    let err = store
+      .edit(&config, "ninja", "language", Some("klingon"))
+      .await
+      .unwrap_err();
+    assert_eq!(
+      Some(&PersonaError::UnknownLanguage(String::from("klingon"))),
+      err.downcast_ref()
+    );
+
+    let err = store
+      .create(
+        &config,
+        Persona {
+          name: String::from("me"),
+          ..Default::default()
+        },
+      )
+      .await
+      .unwrap_err();
+    assert_eq!(
+      Some(&PersonaError::ReservedName(String::from("me"))),
+      err.downcast_ref()
+    );
+
+    // Deleting an edited persona from the config restores the config version.
+    store.delete(&config, "pirate").await?;
+    assert_eq!(None, store.get(&config, "pirate").await?.unwrap().voice);
+
+    let err = store.delete(&config, "pirate").await.unwrap_err();
+    assert_eq!(
+      Some(&PersonaError::Configured(String::from("Pirate"))),
+      err.downcast_ref()
+    );
+
+    Ok(())
+  }
+}
@@ -0,0 +1,372 @@
+use std::{
+  collections::HashMap,
+  hash::Hash,
+  sync::Mutex,
+  time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, UserId};
+
+use crate::config;
+
+/// How many requests can be made in a burst and how long it takes to be able to make one more.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Quota {
+  /// The maximum number of requests that can be made in a burst.
+  pub capacity: u32,
+  /// One request is given back after each interval.
+  #[serde(
+    rename = "refill_interval_secs",
+    serialize_with = "crate::config::serialize_seconds",
+    deserialize_with = "crate::config::deserialize_seconds"
+  )]
+  pub refill_interval: Duration,
+}
+
+impl Quota {
+  pub const fn new(capacity: u32, refill_interval: Duration) -> Self {
+    Self {
+      capacity,
+      refill_interval,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// Applied to every message or command sent by a user.
+  pub user: Quota,
+  /// Applied to every message or command sent in a channel.
+  pub channel: Quota,
+  /// Applied to every invocation of a command by a user.
+  pub command: Quota,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      user: Quota::new(5, Duration::from_secs(4)),
+      channel: Quota::new(15, Duration::from_secs(2)),
+      command: Quota::new(3, Duration::from_secs(5)),
+    }
+  }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+  tokens: f64,
+  last_refill: Instant,
+}
+
+impl TokenBucket {
+  fn new(quota: &Quota, now: Instant) -> Self {
+    Self {
+      tokens: quota.capacity as f64,
+      last_refill: now,
+    }
+  }
+
+  fn refill(&mut self, quota: &Quota, now: Instant) {
+    let elapsed = now.saturating_duration_since(self.last_refill);
+    let refilled = elapsed.as_secs_f64() / quota.refill_interval.as_secs_f64();
+    self.tokens = (self.tokens + refilled).min(quota.capacity as f64);
+    self.last_refill = now;
+  }
+
+  /// How long until a token is available.
+  fn wait_time(&self, quota: &Quota) -> Duration {
+    if self.tokens >= 1.0 {
+      Duration::ZERO
+    } else {
+      quota.refill_interval.mul_f64(1.0 - self.tokens)
+    }
+  }
+
+  fn is_full(&self, quota: &Quota) -> bool {
+    self.tokens >= quota.capacity as f64
+  }
+}
+
+/// Token buckets that share the same quota.
+#[derive(Debug)]
+struct Buckets<K> {
+  quota: Quota,
+  buckets: HashMap<K, TokenBucket>,
+}
+
+/// Full buckets are dropped once there are more buckets than this.
+const MAX_BUCKETS_BEFORE_PRUNING: usize = 10_000;
+
+impl<K: Hash + Eq> Buckets<K> {
+  fn new(quota: Quota) -> Self {
+    Self {
+      quota,
+      buckets: HashMap::new(),
+    }
+  }
+
+  /// Buckets keep their tokens, the new quota applies from now on.
+  fn set_quota(&mut self, quota: Quota) {
+    self.quota = quota;
+  }
+
+  fn wait_time(&mut self, key: &K, now: Instant) -> Duration {
+    let quota = self.quota;
+    match self.buckets.get_mut(key) {
+      None => Duration::ZERO,
+      Some(bucket) => {
+        bucket.refill(&quota, now);
+        bucket.wait_time(&quota)
+      }
+    }
+  }
+
+  fn take(&mut self, key: K, now: Instant) {
+    let quota = self.quota;
+
+    if self.buckets.len() > MAX_BUCKETS_BEFORE_PRUNING {
+      self.buckets.retain(|_, bucket| {
+        bucket.refill(&quota, now);
+        !bucket.is_full(&quota)
+      });
+    }
+
+    let bucket = self
+      .buckets
+      .entry(key)
+      .or_insert_with(|| TokenBucket::new(&quota, now));
+    bucket.refill(&quota, now);
+    bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+  }
+}
+
+/// A request that was rejected because the user is going too fast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttled {
+  /// How long the user has to wait before trying again.
+  pub wait_time: Duration,
+  /// True for the first rejected request while the user waits, the user
+  /// is told to wait once and the next requests are ignored silently.
+  pub should_warn: bool,
+}
+
+#[derive(Debug)]
+struct Limits {
+  users: Buckets<UserId>,
+  channels: Buckets<ChannelId>,
+  commands: Buckets<(UserId, &'static str)>,
+  /// When the users that were told to wait can try again.
+  warned_until: HashMap<UserId, Instant>,
+}
+
+/// Limits how often users can make the bot call external apis.
+#[derive(Debug)]
+pub struct RateLimiter {
+  /// Buckets are checked and taken under the same lock so concurrent
+  /// requests cannot take the same token.
+  limits: Mutex<Limits>,
+}
+
+impl RateLimiter {
+  pub fn new(config: Config) -> Self {
+    Self {
+      limits: Mutex::new(Limits {
+        users: Buckets::new(config.user),
+        channels: Buckets::new(config.channel),
+        commands: Buckets::new(config.command),
+        warned_until: HashMap::new(),
+      }),
+    }
+  }
+
+  /// Returns Ok(()) if the user can send a message to the bot in the channel
+  /// or how long the user has to wait before trying again.
+  #[tracing::instrument(name = "RateLimiter::check_message", skip_all)]
+  pub fn check_message(&self, user_id: UserId, channel_id: ChannelId) -> Result<(), Throttled> {
+    self.check(user_id, channel_id, None, Instant::now())
+  }
+
+  /// Returns Ok(()) if the user can invoke the command in the channel
+  /// or how long the user has to wait before trying again.
+  #[tracing::instrument(name = "RateLimiter::check_command", skip_all)]
+  pub fn check_command(
+    &self,
+    user_id: UserId,
+    channel_id: ChannelId,
+    command: &'static str,
+  ) -> Result<(), Throttled> {
+    self.check(user_id, channel_id, Some(command), Instant::now())
+  }
+
+  /// Tokens are only taken when every bucket has one available
+  /// so a rejected request does not count against the user.
+  fn check(
+    &self,
+    user_id: UserId,
+    channel_id: ChannelId,
+    command: Option<&'static str>,
+    now: Instant,
+  ) -> Result<(), Throttled> {
+    let mut limits = self.limits.lock().unwrap();
+
+    let wait_time = [
+      limits.users.wait_time(&user_id, now),
+      limits.channels.wait_time(&channel_id, now),
+      command
+        .map(|command| limits.commands.wait_time(&(user_id, command), now))
+        .unwrap_or_default(),
+    ]
+    .into_iter()
+    .max()
+    .unwrap_or_default();
+
+    if !wait_time.is_zero() {
+      if limits.warned_until.len() > MAX_BUCKETS_BEFORE_PRUNING {
+        limits.warned_until.retain(|_, until| *until > now);
+      }
+
+      let should_warn = match limits.warned_until.get(&user_id) {
+        Some(until) => *until <= now,
+        None => true,
+      };
+      if should_warn {
+        limits.warned_until.insert(user_id, now + wait_time);
+      }
+
+      return Err(Throttled {
+        wait_time,
+        should_warn,
+      });
+    }
+
+    limits.users.take(user_id, now);
+    limits.channels.take(channel_id, now);
+    if let Some(command) = command {
+      limits.commands.take((user_id, command), now);
+    }
+
+    Ok(())
+  }
+}
+
+impl config::Reloadable for RateLimiter {
+  fn reload(&self, config: &config::Config) {
+    let mut limits = self.limits.lock().unwrap();
+    limits.users.set_quota(config.rate_limit.user);
+    limits.channels.set_quota(config.rate_limit.channel);
+    limits.commands.set_quota(config.rate_limit.command);
+  }
+}
+
+/// The message sent to users that are being rate limited.
+pub fn wait_message(wait_time: Duration) -> String {
+  // Round up so users are not told to wait 0 seconds.
+  let seconds = wait_time.as_secs() + u64::from(wait_time.subsec_nanos() > 0);
+  format!("you are going too fast, try again in {}s", seconds)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn token_bucket_refills_over_time() {
+    let now = Instant::now();
+    let mut buckets = Buckets::new(Quota::new(2, Duration::from_secs(10)));
+
+    buckets.take(1, now);
+    buckets.take(1, now);
+    assert_eq!(Duration::from_secs(10), buckets.wait_time(&1, now));
+    assert_eq!(
+      Duration::from_secs(4),
+      buckets.wait_time(&1, now + Duration::from_secs(6))
+    );
+    assert_eq!(
+      Duration::ZERO,
+      buckets.wait_time(&1, now + Duration::from_secs(10))
+    );
+
+    // Other keys have their own bucket.
+    assert_eq!(Duration::ZERO, buckets.wait_time(&2, now));
+  }
+
+  #[test]
+  fn rejected_requests_do_not_take_tokens() {
+    let now = Instant::now();
+    let limiter = RateLimiter::new(Config {
+      user: Quota::new(1, Duration::from_secs(10)),
+      channel: Quota::new(10, Duration::from_secs(1)),
+      command: Quota::new(10, Duration::from_secs(1)),
+    });
+
+    let (user, channel) = (UserId(1), ChannelId(1));
+
+    assert_eq!(Ok(()), limiter.check(user, channel, Some("echo"), now));
+    assert_eq!(
+      Err(Throttled {
+        wait_time: Duration::from_secs(10),
+        should_warn: true
+      }),
+      limiter.check(user, channel, Some("echo"), now)
+    );
+    assert_eq!(
+      Err(Throttled {
+        wait_time: Duration::from_secs(10),
+        should_warn: false
+      }),
+      limiter.check(user, channel, None, now)
+    );
+
+    // Only the accepted request took a token from the channel.
+    assert_eq!(Ok(()), limiter.check(UserId(2), channel, None, now));
+    let limits = limiter.limits.lock().unwrap();
+    assert_eq!(8.0, limits.channels.buckets[&channel].tokens);
+  }
+
+  #[test]
+  fn users_are_warned_once_per_wait() {
+    let now = Instant::now();
+    let limiter = RateLimiter::new(Config {
+      user: Quota::new(1, Duration::from_secs(10)),
+      ..Config::default()
+    });
+
+    let (user, channel) = (UserId(1), ChannelId(1));
+    let should_warn = |now| {
+      limiter
+        .check(user, channel, None, now)
+        .map_err(|throttled| throttled.should_warn)
+    };
+
+    assert_eq!(Ok(()), should_warn(now));
+    assert_eq!(Err(true), should_warn(now));
+    assert_eq!(Err(false), should_warn(now + Duration::from_secs(5)));
+    assert_eq!(Ok(()), should_warn(now + Duration::from_secs(10)));
+    assert_eq!(Err(true), should_warn(now + Duration::from_secs(10)));
+  }
+
+  #[test]
+  fn test_wait_message() {
+    let tests = vec![
+      (
+        Duration::from_secs(3),
+        "you are going too fast, try again in 3s",
+      ),
+      (
+        Duration::from_millis(2100),
+        "you are going too fast, try again in 3s",
+      ),
+      (
+        Duration::from_millis(1),
+        "you are going too fast, try again in 1s",
+      ),
+    ];
+
+    for (input, expected) in tests {
+      assert_eq!(expected, wait_message(input), "input={:?}", input);
+    }
+  }
+}
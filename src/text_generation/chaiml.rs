@@ -0,0 +1,175 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tracing::{error, info};
+
+use super::Profile;
+use crate::{
+  contracts::{self, http::PostOptions},
+  conversation::Conversation,
+};
+
+#[derive(Debug, Serialize)]
+struct ChatBotRequest<'a> {
+  pub text: &'a str,
+  pub temperature: f32,
+  pub repetition_penalty: f32,
+  pub top_p: f32,
+  pub top_k: u32,
+  pub response_length: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatBotResponse {
+  pub data: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  #[serde(rename = "developer_uuid")]
+  pub chaiml_developer_uuid: String,
+  #[serde(rename = "key")]
+  pub chaiml_key: String,
+}
+
+/// Generates text with the ChaiML GPT-J api.
+pub struct ChaiMl {
+  config: Config,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl ChaiMl {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      http_client,
+      config,
+    }
+  }
+}
+
+#[async_trait]
+impl contracts::text_generation::TextGeneration for ChaiMl {
+  #[tracing::instrument(name = "ChaiMl::generate", skip_all)]
+  async fn generate(&self, conversation: &Conversation, profile: &Profile) -> Result<String> {
+    let text = conversation.to_string();
+
+    let body = ChatBotRequest {
+      text: &text,
+      temperature: profile.temperature,
+      repetition_penalty: profile.repetition_penalty,
+      top_p: profile.top_p,
+      top_k: profile.top_k,
+      response_length: profile.max_tokens,
+    };
+
+    let response = self
+      .http_client
+      .post(
+        "https://model-api-shdxwd54ta-nw.a.run.app/generate/gptj",
+        serde_json::to_vec(&body)?,
+        Some(PostOptions {
+          headers: Some(vec![
+            (
+              "Host".to_string(),
+              "model-api-shdxwd54ta-nw.a.run.app".to_string(),
+            ),
+            ("Referer".to_string(), "https://chai.ml/".to_string()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+            (
+              "developer_uid".to_string(),
+              self.config.chaiml_developer_uuid.clone(),
+            ),
+            ("developer_key".to_string(), self.config.chaiml_key.clone()),
+            ("Origin".to_string(), "https://chai.ml".to_string()),
+          ]),
+          timeout: Some(Duration::from_secs(30)),
+        }),
+      )
+      .await?;
+
+    match serde_json::from_slice::<ChatBotResponse>(&response.body) {
+      Err(err) => {
+        let error = Err(anyhow::anyhow!(
+          "unexpected chat bot response. request_body={:?}, response={:?} error={:?}",
+          &body,
+          String::from_utf8_lossy(&response.body),
+          err
+        ));
+        error!("error={:?}", error);
+        error
+      }
+      Ok(body) => {
+        info!("text generated. text={}", &body.data);
+        Ok(super::remove_speaker_prefix(&body.data, conversation))
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod generate_tests {
+  use bytes::Bytes;
+
+  use crate::contracts::{
+    http::{MockHttpClient, PostResponse},
+    text_generation::TextGeneration,
+  };
+
+  use super::*;
+
+  #[tokio::test]
+  async fn removes_unnecesary_prefix_from_generated_text() -> Result<(), Box<dyn std::error::Error>>
+  {
+    let tests = vec![
+      (
+        "Eliza: something something, blah blah",
+        "something something, blah blah",
+      ),
+      (
+        "Eliza:something something, blah blah",
+        "something something, blah blah",
+      ),
+      (
+        "Me: something something, blah blah",
+        "something something, blah blah",
+      ),
+      (
+        "Me:something something, blah blah",
+        "something something, blah blah",
+      ),
+    ];
+
+    for (input, expected) in tests.into_iter() {
+      let mut http_client = MockHttpClient::new();
+
+      http_client.expect_post().returning(move |_, _, _| {
+        Ok(PostResponse {
+          body: Bytes::from(serde_json::to_string(&serde_json::json!({
+            "data": input
+          }))?),
+        })
+      });
+
+      let generator = ChaiMl::new(
+        Config {
+          chaiml_developer_uuid: "uuid".to_string(),
+          chaiml_key: "key".to_string(),
+        },
+        Arc::new(http_client),
+      );
+
+      let generated_text = generator
+        .generate(
+          &Conversation::new("some context", "Eliza"),
+          &Profile::default(),
+        )
+        .await?;
+
+      assert_eq!(expected, generated_text);
+    }
+
+    Ok(())
+  }
+}
@@ -0,0 +1,175 @@
+//! Text generation backends and the [TextGenerator] that picks
+//! the backend configured for each guild.
+
+pub mod chaiml;
+pub mod ollama;
+pub mod openai;
+mod profile;
+
+use std::{
+  collections::BTreeMap,
+  sync::{Arc, RwLock},
+};
+
+use anyhow::Result;
+use retry::{ExponentialBackoff, Retry};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::GuildId;
+
+pub use profile::{Parameter, Profile, ProfileError};
+
+use crate::{
+  config,
+  contracts::{self, text_generation::TextGeneration},
+  conversation::Conversation,
+};
+
+/// The name the user messages are prefixed with in a conversation.
+pub const USER_NAME: &str = "Me";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+  #[default]
+  Chaiml,
+  /// Any api compatible with the OpenAI chat completions api, like the llama.cpp server.
+  Openai,
+  Ollama,
+}
+
+impl std::fmt::Display for Backend {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Backend::Chaiml => "chaiml",
+      Backend::Openai => "openai",
+      Backend::Ollama => "ollama",
+    })
+  }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// The backend used when the guild does not have one in `guild_backends`.
+  pub backend: Backend,
+  /// Backends used by specific guilds, by guild id.
+  pub guild_backends: BTreeMap<String, Backend>,
+  /// The sampling parameters used when the guild has not changed them.
+  pub profile: Profile,
+  pub chaiml: chaiml::Config,
+  pub openai: openai::Config,
+  pub ollama: ollama::Config,
+}
+
+impl Config {
+  pub fn backend_for(&self, guild_id: Option<GuildId>) -> Backend {
+    guild_id
+      .and_then(|guild_id| self.guild_backends.get(&guild_id.0.to_string()).copied())
+      .unwrap_or(self.backend)
+  }
+
+  /// Returns true if the backend is used by default or by any guild.
+  pub fn uses(&self, backend: Backend) -> bool {
+    self.backend == backend || self.guild_backends.values().any(|b| *b == backend)
+  }
+}
+
+/// Generates text with the backend configured for the guild the message was sent in.
+pub struct TextGenerator {
+  config: RwLock<Config>,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl TextGenerator {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      http_client,
+      config: RwLock::new(config),
+    }
+  }
+
+  fn backend(&self, guild_id: Option<GuildId>) -> Box<dyn TextGeneration> {
+    let config = self.config.read().unwrap();
+    let http_client = Arc::clone(&self.http_client);
+
+    match config.backend_for(guild_id) {
+      Backend::Chaiml => Box::new(chaiml::ChaiMl::new(config.chaiml.clone(), http_client)),
+      Backend::Openai => Box::new(openai::OpenAi::new(config.openai.clone(), http_client)),
+      Backend::Ollama => Box::new(ollama::Ollama::new(config.ollama.clone(), http_client)),
+    }
+  }
+
+  /// Returns the backend used by the guild.
+  pub fn backend_for(&self, guild_id: Option<GuildId>) -> Backend {
+    self.config.read().unwrap().backend_for(guild_id)
+  }
+
+  /// Returns the profile used when the guild has not changed it.
+  pub fn default_profile(&self) -> Profile {
+    self.config.read().unwrap().profile
+  }
+
+  /// Generates the next bot message in the conversation. If you want it to talk about soccer,
+  /// pass a conversation about soccer.
+  #[tracing::instrument(name = "TextGenerator::generate", skip_all)]
+  pub async fn generate(
+    &self,
+    guild_id: Option<GuildId>,
+    conversation: &Conversation,
+    profile: &Profile,
+  ) -> Result<String> {
+    let backend = self.backend(guild_id);
+
+    Retry::new()
+      .retries(3)
+      .backoff(ExponentialBackoff::recommended())
+      .exec(|| backend.generate(conversation, profile))
+      .await
+  }
+}
+
+impl config::Reloadable for TextGenerator {
+  fn reload(&self, config: &config::Config) {
+    *self.config.write().unwrap() = config.text_generation.clone();
+  }
+}
+
+/// Removes the speaker name some models start the generated message with.
+fn remove_speaker_prefix(text: &str, conversation: &Conversation) -> String {
+  let mut names = vec![conversation.bot_name(), USER_NAME];
+  names.extend(conversation.user_names(&conversation.turns));
+
+  for name in names {
+    if let Some(text) = text
+      .strip_prefix(name)
+      .and_then(|text| text.strip_prefix(':'))
+    {
+      return text.trim_start().to_owned();
+    }
+  }
+
+  text.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn picks_the_backend_configured_for_the_guild() {
+    let config: Config = toml::from_str(
+      r#"
+        backend = "openai"
+
+        [guild_backends]
+        123 = "ollama"
+      "#,
+    )
+    .unwrap();
+
+    assert_eq!(Backend::Ollama, config.backend_for(Some(GuildId(123))));
+    assert_eq!(Backend::Openai, config.backend_for(Some(GuildId(456))));
+    assert_eq!(Backend::Openai, config.backend_for(None));
+    assert!(!config.uses(Backend::Chaiml));
+  }
+}
@@ -0,0 +1,126 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tracing::info;
+
+use super::{Profile, USER_NAME};
+use crate::{
+  contracts::{self, http::PostOptions},
+  conversation::Conversation,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub base_url: String,
+  pub model: String,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      base_url: String::from("http://localhost:11434"),
+      model: String::new(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+struct GenerateRequest<'a> {
+  model: &'a str,
+  prompt: String,
+  stream: bool,
+  options: GenerateOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct GenerateOptions {
+  temperature: f32,
+  repeat_penalty: f32,
+  top_p: f32,
+  top_k: u32,
+  num_predict: u32,
+  stop: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateResponse {
+  response: String,
+}
+
+/// Generates text with a local Ollama server.
+pub struct Ollama {
+  config: Config,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl Ollama {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      config,
+      http_client,
+    }
+  }
+}
+
+#[async_trait]
+impl contracts::text_generation::TextGeneration for Ollama {
+  #[tracing::instrument(name = "Ollama::generate", skip_all)]
+  async fn generate(&self, conversation: &Conversation, profile: &Profile) -> Result<String> {
+    // The model completes the conversation, so stop before it starts writing the user messages.
+    let mut stop = vec![format!("\n{}:", USER_NAME)];
+    for name in conversation.user_names(&conversation.turns) {
+      let name = format!("\n{}:", name);
+      if !stop.contains(&name) {
+        stop.push(name);
+      }
+    }
+
+    let body = GenerateRequest {
+      model: &self.config.model,
+      prompt: format!("{}{}:", conversation, conversation.bot_name()),
+      stream: false,
+      options: GenerateOptions {
+        temperature: profile.temperature,
+        repeat_penalty: profile.repetition_penalty,
+        top_p: profile.top_p,
+        top_k: profile.top_k,
+        num_predict: profile.max_tokens,
+        stop,
+      },
+    };
+
+    let response = self
+      .http_client
+      .post(
+        &format!(
+          "{}/api/generate",
+          self.config.base_url.trim_end_matches('/')
+        ),
+        serde_json::to_vec(&body)?,
+        Some(PostOptions {
+          headers: Some(vec![(
+            "Content-Type".to_string(),
+            "application/json".to_string(),
+          )]),
+          timeout: Some(Duration::from_secs(60)),
+        }),
+      )
+      .await?;
+
+    let response: GenerateResponse = serde_json::from_slice(&response.body).with_context(|| {
+      format!(
+        "unexpected ollama response. response={:?}",
+        String::from_utf8_lossy(&response.body)
+      )
+    })?;
+
+    info!("text generated. text={}", &response.response);
+
+    Ok(super::remove_speaker_prefix(
+      response.response.trim(),
+      conversation,
+    ))
+  }
+}
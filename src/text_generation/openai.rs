@@ -0,0 +1,265 @@
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::{sync::Arc, time::Duration};
+use tracing::info;
+
+use super::{Profile, USER_NAME};
+use crate::{
+  contracts::{self, http::PostOptions},
+  conversation::{Conversation, Speaker},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// The llama.cpp server also works here, for example `http://localhost:8080/v1`.
+  pub base_url: String,
+  pub api_key: String,
+  pub model: String,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      base_url: String::from("https://api.openai.com/v1"),
+      api_key: String::new(),
+      model: String::from("gpt-3.5-turbo"),
+    }
+  }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct Message {
+  role: &'static str,
+  content: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatCompletionRequest<'a> {
+  model: &'a str,
+  messages: Vec<Message>,
+  max_tokens: u32,
+  temperature: f32,
+  top_p: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatCompletionResponse {
+  choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+  message: ResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+  content: String,
+}
+
+/// Generates text with an api compatible with the OpenAI chat completions api.
+pub struct OpenAi {
+  config: Config,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl OpenAi {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      config,
+      http_client,
+    }
+  }
+}
+
+#[async_trait]
+impl contracts::text_generation::TextGeneration for OpenAi {
+  #[tracing::instrument(name = "OpenAi::generate", skip_all)]
+  async fn generate(&self, conversation: &Conversation, profile: &Profile) -> Result<String> {
+    let body = ChatCompletionRequest {
+      model: &self.config.model,
+      messages: conversation_to_messages(conversation),
+      max_tokens: profile.max_tokens,
+      temperature: profile.temperature,
+      top_p: profile.top_p,
+    };
+
+    let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+    if !self.config.api_key.is_empty() {
+      headers.push((
+        "Authorization".to_string(),
+        format!("Bearer {}", self.config.api_key),
+      ));
+    }
+
+    let response = self
+      .http_client
+      .post(
+        &format!(
+          "{}/chat/completions",
+          self.config.base_url.trim_end_matches('/')
+        ),
+        serde_json::to_vec(&body)?,
+        Some(PostOptions {
+          headers: Some(headers),
+          timeout: Some(Duration::from_secs(60)),
+        }),
+      )
+      .await?;
+
+    let response: ChatCompletionResponse =
+      serde_json::from_slice(&response.body).with_context(|| {
+        format!(
+          "unexpected chat completion response. response={:?}",
+          String::from_utf8_lossy(&response.body)
+        )
+      })?;
+
+    let text = response
+      .choices
+      .into_iter()
+      .next()
+      .map(|choice| choice.message.content)
+      .context("chat completion response has no choices")?;
+
+    info!("text generated. text={}", &text);
+
+    Ok(super::remove_speaker_prefix(text.trim(), conversation))
+  }
+}
+
+/// Turns the conversation into chat messages. Messages sent in a channel
+/// conversation start with the name of the user who sent them.
+fn conversation_to_messages(conversation: &Conversation) -> Vec<Message> {
+  let mut messages = transcript_to_messages(&conversation.context(), conversation.bot_name());
+
+  messages.extend(conversation.turns.iter().map(|turn| match turn.speaker {
+    Speaker::Bot => Message {
+      role: "assistant",
+      content: turn.text.clone(),
+    },
+    Speaker::User => Message {
+      role: "user",
+      content: match &turn.author {
+        None => turn.text.clone(),
+        Some(author) => format!("{}: {}", author, turn.text),
+      },
+    },
+  }));
+
+  messages
+}
+
+/// Turns a conversation like `Me: hi\nEliza: hello` into chat messages.
+/// Lines before the first message describe the conversation and become the system prompt.
+fn transcript_to_messages(context: &str, bot_name: &str) -> Vec<Message> {
+  let user_prefix = format!("{}:", USER_NAME);
+  let bot_prefix = format!("{}:", bot_name);
+
+  let mut preamble = Vec::new();
+  let mut messages: Vec<Message> = Vec::new();
+
+  for line in context
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty())
+  {
+    let (role, content) = if let Some(content) = line.strip_prefix(&user_prefix) {
+      ("user", content)
+    } else if let Some(content) = line.strip_prefix(&bot_prefix) {
+      ("assistant", content)
+    } else {
+      match messages.last_mut() {
+        None => preamble.push(line),
+        Some(message) => {
+          message.content.push('\n');
+          message.content.push_str(line);
+        }
+      }
+      continue;
+    };
+
+    messages.push(Message {
+      role,
+      content: content.trim().to_owned(),
+    });
+  }
+
+  let mut system_prompt = format!(
+    "You are {}, chatting with a user. Reply with a single short message.",
+    bot_name
+  );
+  if !preamble.is_empty() {
+    system_prompt.push('\n');
+    system_prompt.push_str(&preamble.join("\n"));
+  }
+
+  std::iter::once(Message {
+    role: "system",
+    content: system_prompt,
+  })
+  .chain(messages)
+  .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::conversation::Turn;
+
+  #[test]
+  fn turns_the_conversation_into_chat_messages() {
+    let messages = transcript_to_messages(
+      "Eliza likes soccer.\n\nMe: hi\nEliza: hello\nhow are you?\nMe: fine",
+      "Eliza",
+    );
+
+    let expected = vec![
+      Message {
+        role: "system",
+        content: "You are Eliza, chatting with a user. Reply with a single short message.\nEliza likes soccer.".to_owned(),
+      },
+      Message {
+        role: "user",
+        content: "hi".to_owned(),
+      },
+      Message {
+        role: "assistant",
+        content: "hello\nhow are you?".to_owned(),
+      },
+      Message {
+        role: "user",
+        content: "fine".to_owned(),
+      },
+    ];
+
+    assert_eq!(expected, messages);
+  }
+
+  #[test]
+  fn labels_the_messages_of_channel_conversations() {
+    let mut conversation = Conversation::new("Me: hi\nEliza: hello", "Eliza");
+    conversation.push(Turn {
+      author: Some(String::from("Ana")),
+      ..Turn::new(Speaker::User, "who are you?")
+    });
+    conversation.push(Turn::new(Speaker::Bot, "Eliza"));
+
+    let messages = conversation_to_messages(&conversation);
+
+    assert_eq!(
+      vec![
+        ("user", "hi"),
+        ("assistant", "hello"),
+        ("user", "Ana: who are you?"),
+        ("assistant", "Eliza")
+      ],
+      messages[1..]
+        .iter()
+        .map(|message| (message.role, message.content.as_str()))
+        .collect::<Vec<_>>()
+    );
+  }
+}
@@ -0,0 +1,212 @@
+use std::{collections::BTreeMap, fmt, ops::RangeInclusive, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A sampling parameter of a [Profile] that can be changed at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Parameter {
+  Temperature,
+  RepetitionPenalty,
+  TopP,
+  TopK,
+  MaxTokens,
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum ProfileError {
+  #[error("unknown parameter {0}, the parameters are: {}", Parameter::names().join(", "))]
+  UnknownParameter(String),
+  #[error("{name} must be between {} and {}", .range.start(), .range.end())]
+  OutOfRange {
+    name: &'static str,
+    range: RangeInclusive<f32>,
+  },
+  #[error("{0} must be a whole number")]
+  NotInteger(&'static str),
+}
+
+impl Parameter {
+  pub const ALL: [Parameter; 5] = [
+    Parameter::Temperature,
+    Parameter::RepetitionPenalty,
+    Parameter::TopP,
+    Parameter::TopK,
+    Parameter::MaxTokens,
+  ];
+
+  pub fn name(self) -> &'static str {
+    match self {
+      Parameter::Temperature => "temperature",
+      Parameter::RepetitionPenalty => "repetition_penalty",
+      Parameter::TopP => "top_p",
+      Parameter::TopK => "top_k",
+      Parameter::MaxTokens => "max_tokens",
+    }
+  }
+
+  fn names() -> Vec<&'static str> {
+    Self::ALL.iter().map(|parameter| parameter.name()).collect()
+  }
+
+  fn range(self) -> RangeInclusive<f32> {
+    match self {
+      Parameter::Temperature => 0.0..=2.0,
+      Parameter::RepetitionPenalty => 1.0..=2.0,
+      Parameter::TopP => 0.0..=1.0,
+      Parameter::TopK => 1.0..=200.0,
+      Parameter::MaxTokens => 1.0..=1024.0,
+    }
+  }
+
+  fn is_integer(self) -> bool {
+    matches!(self, Parameter::TopK | Parameter::MaxTokens)
+  }
+
+  pub fn validate(self, value: f32) -> Result<(), ProfileError> {
+    let range = self.range();
+
+    if !range.contains(&value) {
+      return Err(ProfileError::OutOfRange {
+        name: self.name(),
+        range,
+      });
+    }
+
+    if self.is_integer() && value.fract() != 0.0 {
+      return Err(ProfileError::NotInteger(self.name()));
+    }
+
+    Ok(())
+  }
+}
+
+impl FromStr for Parameter {
+  type Err = ProfileError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Self::ALL
+      .into_iter()
+      .find(|parameter| parameter.name() == s)
+      .ok_or_else(|| ProfileError::UnknownParameter(s.to_owned()))
+  }
+}
+
+// Deserialized from a string because TOML table keys are always strings.
+impl<'de> Deserialize<'de> for Parameter {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    String::deserialize(deserializer)?
+      .parse()
+      .map_err(serde::de::Error::custom)
+  }
+}
+
+impl fmt::Display for Parameter {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+/// The sampling parameters sent to the text generation backends.
+/// Backends ignore the parameters they do not support.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+  pub temperature: f32,
+  pub repetition_penalty: f32,
+  pub top_p: f32,
+  pub top_k: u32,
+  /// The maximum length of the generated message.
+  pub max_tokens: u32,
+}
+
+impl Default for Profile {
+  fn default() -> Self {
+    Self {
+      temperature: 0.9,
+      repetition_penalty: 1.1,
+      top_p: 1.0,
+      top_k: 40,
+      max_tokens: 128,
+    }
+  }
+}
+
+impl Profile {
+  pub fn get(&self, parameter: Parameter) -> f32 {
+    match parameter {
+      Parameter::Temperature => self.temperature,
+      Parameter::RepetitionPenalty => self.repetition_penalty,
+      Parameter::TopP => self.top_p,
+      Parameter::TopK => self.top_k as f32,
+      Parameter::MaxTokens => self.max_tokens as f32,
+    }
+  }
+
+  pub fn set(&mut self, parameter: Parameter, value: f32) -> Result<(), ProfileError> {
+    parameter.validate(value)?;
+
+    match parameter {
+      Parameter::Temperature => self.temperature = value,
+      Parameter::RepetitionPenalty => self.repetition_penalty = value,
+      Parameter::TopP => self.top_p = value,
+      Parameter::TopK => self.top_k = value as u32,
+      Parameter::MaxTokens => self.max_tokens = value as u32,
+    }
+
+    Ok(())
+  }
+
+  /// Returns the profile with the parameters in `overrides` replaced.
+  /// Invalid overrides are ignored.
+  pub fn with_overrides(mut self, overrides: &BTreeMap<Parameter, f32>) -> Self {
+    for (parameter, value) in overrides {
+      let _ = self.set(*parameter, *value);
+    }
+    self
+  }
+
+  pub fn validate(&self) -> Vec<ProfileError> {
+    Parameter::ALL
+      .into_iter()
+      .filter_map(|parameter| parameter.validate(self.get(parameter)).err())
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn validates_parameter_ranges() {
+    let mut profile = Profile::default();
+
+    assert_eq!(Ok(()), profile.set(Parameter::Temperature, 0.7));
+    assert_eq!(0.7, profile.temperature);
+
+    assert_eq!(
+      Err(ProfileError::OutOfRange {
+        name: "temperature",
+        range: 0.0..=2.0
+      }),
+      profile.set(Parameter::Temperature, 2.5)
+    );
+    assert_eq!(
+      Err(ProfileError::NotInteger("top_k")),
+      profile.set(Parameter::TopK, 10.5)
+    );
+    assert_eq!(0.7, profile.temperature);
+    assert_eq!(40, profile.top_k);
+  }
+
+  #[test]
+  fn applies_overrides() {
+    let overrides = BTreeMap::from([(Parameter::MaxTokens, 64.0), (Parameter::TopP, 5.0)]);
+
+    let profile = Profile::default().with_overrides(&overrides);
+
+    assert_eq!(64, profile.max_tokens);
+    assert_eq!(Profile::default().top_p, profile.top_p);
+  }
+}
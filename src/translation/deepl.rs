@@ -0,0 +1,119 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{Provider, TranslationError};
+use crate::contracts::{self, http::PostOptions};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// Paid accounts use `https://api.deepl.com/v2`.
+  pub base_url: String,
+  pub api_key: String,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      base_url: String::from("https://api-free.deepl.com/v2"),
+      api_key: String::new(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+struct TranslateRequest<'a> {
+  text: [&'a str; 1],
+  source_lang: String,
+  target_lang: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranslateResponse {
+  translations: Vec<Translation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Translation {
+  text: String,
+}
+
+/// Translates with the DeepL api or apis compatible with it.
+pub struct DeepL {
+  config: Config,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl DeepL {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      config,
+      http_client,
+    }
+  }
+}
+
+#[async_trait]
+impl contracts::translation::Translator for DeepL {
+  #[tracing::instrument(name = "DeepL::translate", skip_all)]
+  async fn translate(&self, text: &str, from_lang: &str, to_lang: &str) -> Result<String> {
+    let body = TranslateRequest {
+      text: [text],
+      source_lang: source_lang(from_lang),
+      target_lang: target_lang(to_lang),
+    };
+
+    let response = self
+      .http_client
+      .post(
+        &format!("{}/translate", self.config.base_url.trim_end_matches('/')),
+        serde_json::to_vec(&body)?,
+        Some(PostOptions {
+          headers: Some(vec![
+            ("Content-Type".to_string(), "application/json".to_string()),
+            (
+              "Authorization".to_string(),
+              format!("DeepL-Auth-Key {}", self.config.api_key),
+            ),
+          ]),
+          timeout: Some(Duration::from_secs(30)),
+        }),
+      )
+      .await?;
+
+    let unexpected_format = || TranslationError::UnexpectedFormat {
+      provider: Provider::Deepl,
+      response: String::from_utf8_lossy(&response.body).into_owned(),
+    };
+
+    let translated_text = serde_json::from_slice::<TranslateResponse>(&response.body)
+      .map_err(|_| unexpected_format())?
+      .translations
+      .into_iter()
+      .next()
+      .ok_or_else(unexpected_format)?
+      .text;
+
+    info!("translation={}", &translated_text);
+
+    Ok(translated_text)
+  }
+}
+
+/// DeepL source languages have no region, like `PT`.
+fn source_lang(lang: &str) -> String {
+  lang.split('-').next().unwrap_or(lang).to_uppercase()
+}
+
+/// DeepL requires the region of a few target languages, like `PT-BR`.
+fn target_lang(lang: &str) -> String {
+  match lang.to_uppercase().as_str() {
+    "EN" => String::from("EN-US"),
+    "PT" => String::from("PT-BR"),
+    lang => lang.to_owned(),
+  }
+}
@@ -0,0 +1,76 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tracing::info;
+
+use super::{Provider, TranslationError};
+use crate::contracts::{self, http::GetOptions};
+
+/// Translates with the endpoint used by the Google Translate browser extension.
+/// It needs no api key but it is undocumented and may change at any time.
+pub struct Google {
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl Google {
+  pub fn new(http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self { http_client }
+  }
+}
+
+#[async_trait]
+impl contracts::translation::Translator for Google {
+  #[tracing::instrument(name = "Google::translate", skip_all)]
+  async fn translate(&self, text: &str, from_lang: &str, to_lang: &str) -> Result<String> {
+    let response = self
+      .http_client
+      .get(
+        "https://translate.googleapis.com/translate_a/single?client=gtx",
+        Some(GetOptions {
+          headers: None,
+          query: Some(vec![
+            ("sl".to_string(), from_lang.to_string()),
+            ("tl".to_string(), to_lang.to_string()),
+            ("dt".to_string(), "t".to_string()),
+            ("q".to_string(), text.to_string()),
+          ]),
+          timeout: Some(Duration::from_secs(30)),
+        }),
+      )
+      .await?;
+
+    // Google answers with an html page when it rate limits the bot, the headers tell why.
+    let unexpected_format = || TranslationError::UnexpectedFormat {
+      provider: Provider::Google,
+      response: format!(
+        "{} headers={:?}",
+        String::from_utf8_lossy(&response.body),
+        response.headers
+      ),
+    };
+
+    let body: serde_json::Value =
+      serde_json::from_slice(&response.body).map_err(|_| unexpected_format())?;
+
+    let translations = match &body[0] {
+      serde_json::Value::Array(translations) => translations,
+      _ => return Err(unexpected_format().into()),
+    };
+
+    let mut phrases = Vec::with_capacity(translations.len());
+
+    for translation in translations.iter() {
+      match &translation[0] {
+        serde_json::Value::String(phrase) => phrases.push(phrase.as_str()),
+        _ => return Err(unexpected_format().into()),
+      }
+    }
+
+    let translated_text = phrases.join("");
+
+    info!("translation={}", &translated_text);
+
+    Ok(translated_text)
+  }
+}
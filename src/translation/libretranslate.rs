@@ -0,0 +1,97 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{Provider, TranslationError};
+use crate::contracts::{self, http::PostOptions};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// A self hosted server works here, for example `http://localhost:5000`.
+  pub base_url: String,
+  /// Only needed by servers that require api keys.
+  pub api_key: String,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      base_url: String::from("https://libretranslate.com"),
+      api_key: String::new(),
+    }
+  }
+}
+
+#[derive(Debug, Serialize)]
+struct TranslateRequest<'a> {
+  q: &'a str,
+  source: &'a str,
+  target: &'a str,
+  format: &'static str,
+  #[serde(skip_serializing_if = "str::is_empty")]
+  api_key: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TranslateResponse {
+  translated_text: String,
+}
+
+/// Translates with a LibreTranslate server.
+pub struct LibreTranslate {
+  config: Config,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl LibreTranslate {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      config,
+      http_client,
+    }
+  }
+}
+
+#[async_trait]
+impl contracts::translation::Translator for LibreTranslate {
+  #[tracing::instrument(name = "LibreTranslate::translate", skip_all)]
+  async fn translate(&self, text: &str, from_lang: &str, to_lang: &str) -> Result<String> {
+    let body = TranslateRequest {
+      q: text,
+      source: from_lang,
+      target: to_lang,
+      format: "text",
+      api_key: &self.config.api_key,
+    };
+
+    let response = self
+      .http_client
+      .post(
+        &format!("{}/translate", self.config.base_url.trim_end_matches('/')),
+        serde_json::to_vec(&body)?,
+        Some(PostOptions {
+          headers: Some(vec![(
+            "Content-Type".to_string(),
+            "application/json".to_string(),
+          )]),
+          timeout: Some(Duration::from_secs(30)),
+        }),
+      )
+      .await?;
+
+    let response: TranslateResponse =
+      serde_json::from_slice(&response.body).map_err(|_| TranslationError::UnexpectedFormat {
+        provider: Provider::Libretranslate,
+        response: String::from_utf8_lossy(&response.body).into_owned(),
+      })?;
+
+    info!("translation={}", &response.translated_text);
+
+    Ok(response.translated_text)
+  }
+}
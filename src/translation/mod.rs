@@ -0,0 +1,344 @@
+//! Translation providers and the [Translation] chain that falls back
+//! to the next provider when one of them fails.
+
+pub mod deepl;
+pub mod google;
+pub mod libretranslate;
+
+use std::{
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, RwLock,
+  },
+  time::Duration,
+};
+
+use anyhow::Result;
+use retry::{ExponentialBackoff, Retry};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use crate::{
+  config,
+  contracts::{self, translation::Translator},
+  utils,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+  /// The endpoint used by the Google Translate browser extension, it needs no api key.
+  Google,
+  Libretranslate,
+  Deepl,
+}
+
+impl std::fmt::Display for Provider {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Provider::Google => "google",
+      Provider::Libretranslate => "libretranslate",
+      Provider::Deepl => "deepl",
+    })
+  }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TranslationError {
+  #[error("{provider} returned unexpected format. response_body={response:?}")]
+  UnexpectedFormat {
+    provider: Provider,
+    response: String,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// The providers are tried in order until one of them translates the text.
+  pub providers: Vec<Provider>,
+  /// How long translations are cached, they are not cached when it is 0.
+  #[serde(
+    rename = "cache_ttl_secs",
+    serialize_with = "crate::config::serialize_seconds",
+    deserialize_with = "crate::config::deserialize_seconds"
+  )]
+  pub cache_ttl: Duration,
+  pub libretranslate: libretranslate::Config,
+  pub deepl: deepl::Config,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      providers: vec![Provider::Google],
+      // 7 days
+      cache_ttl: Duration::from_secs(60 * 60 * 24 * 7),
+      libretranslate: libretranslate::Config::default(),
+      deepl: deepl::Config::default(),
+    }
+  }
+}
+
+/// How many translations were found in the cache since the bot started.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+  pub hits: u64,
+  pub misses: u64,
+}
+
+/// Translates with the configured providers and caches the translations.
+pub struct Translation {
+  config: RwLock<Config>,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+  cache: Arc<dyn contracts::cache::Cache>,
+  cache_hits: AtomicU64,
+  cache_misses: AtomicU64,
+}
+
+impl Translation {
+  pub fn new(
+    config: Config,
+    http_client: Arc<dyn contracts::http::HttpClient>,
+    cache: Arc<dyn contracts::cache::Cache>,
+  ) -> Self {
+    Self {
+      config: RwLock::new(config),
+      http_client,
+      cache,
+      cache_hits: AtomicU64::new(0),
+      cache_misses: AtomicU64::new(0),
+    }
+  }
+
+  pub fn cache_stats(&self) -> CacheStats {
+    CacheStats {
+      hits: self.cache_hits.load(Ordering::Relaxed),
+      misses: self.cache_misses.load(Ordering::Relaxed),
+    }
+  }
+
+  fn providers(&self) -> Vec<(Provider, Box<dyn Translator>)> {
+    let config = self.config.read().unwrap();
+
+    config
+      .providers
+      .iter()
+      .map(|provider| {
+        let http_client = Arc::clone(&self.http_client);
+
+        let translator: Box<dyn Translator> = match provider {
+          Provider::Google => Box::new(google::Google::new(http_client)),
+          Provider::Libretranslate => Box::new(libretranslate::LibreTranslate::new(
+            config.libretranslate.clone(),
+            http_client,
+          )),
+          Provider::Deepl => Box::new(deepl::DeepL::new(config.deepl.clone(), http_client)),
+        };
+
+        (*provider, translator)
+      })
+      .collect()
+  }
+
+  #[tracing::instrument(skip_all, fields(
+    from_lang = %from_lang,
+    to_lang = %to_lang,
+    text = %text
+  ))]
+  pub async fn translate(&self, text: &str, from_lang: &str, to_lang: &str) -> Result<String> {
+    let providers = self.providers();
+    let cache_ttl = self.config.read().unwrap().cache_ttl;
+
+    if !cache_ttl.is_zero() {
+      if let Some(translated_text) = self
+        .cached_translation(&providers, text, from_lang, to_lang)
+        .await
+      {
+        return Ok(translated_text);
+      }
+    }
+
+    let (provider, translated_text) = Retry::new()
+      .retries(3)
+      .backoff(ExponentialBackoff::recommended())
+      .exec(|| async {
+        let mut last_error = anyhow::anyhow!("no translation provider is configured");
+
+        for (provider, translator) in providers.iter() {
+          match translator.translate(text, from_lang, to_lang).await {
+            Ok(translated_text) => return Ok((*provider, translated_text)),
+            Err(err) => {
+              warn!(
+                "translation provider failed, trying the next one. provider={} error={:?}",
+                provider, err
+              );
+              last_error = err;
+            }
+          }
+        }
+
+        Err(last_error)
+      })
+      .await?;
+
+    if !cache_ttl.is_zero() {
+      if let Err(err) = self
+        .cache
+        .put(
+          cache_key(provider, from_lang, to_lang, text),
+          translated_text.as_bytes().to_vec(),
+          Some(cache_ttl),
+        )
+        .await
+      {
+        error!("unable to cache translation. error={:?}", err);
+      }
+    }
+
+    Ok(translated_text)
+  }
+
+  /// Returns the translation made by any of the providers if it is cached.
+  async fn cached_translation(
+    &self,
+    providers: &[(Provider, Box<dyn Translator>)],
+    text: &str,
+    from_lang: &str,
+    to_lang: &str,
+  ) -> Option<String> {
+    for (provider, _) in providers {
+      match self
+        .cache
+        .get(&cache_key(*provider, from_lang, to_lang, text))
+        .await
+      {
+        Err(err) => error!("unable to read cached translation. error={:?}", err),
+        Ok(None) => {}
+        Ok(Some(bytes)) => {
+          let hits = self.cache_hits.fetch_add(1, Ordering::Relaxed) + 1;
+          info!(
+            "translation cache hit. hits={} misses={}",
+            hits,
+            self.cache_misses.load(Ordering::Relaxed)
+          );
+          return Some(String::from_utf8_lossy(&bytes).into_owned());
+        }
+      }
+    }
+
+    let misses = self.cache_misses.fetch_add(1, Ordering::Relaxed) + 1;
+    info!(
+      "translation cache miss. hits={} misses={}",
+      self.cache_hits.load(Ordering::Relaxed),
+      misses
+    );
+
+    None
+  }
+}
+
+fn cache_key(provider: Provider, from_lang: &str, to_lang: &str, text: &str) -> Vec<u8> {
+  format!(
+    "translation:{}:{}:{}:{}",
+    provider,
+    from_lang,
+    to_lang,
+    utils::sha256_hex(text.as_bytes())
+  )
+  .into_bytes()
+}
+
+impl config::Reloadable for Translation {
+  fn reload(&self, config: &config::Config) {
+    *self.config.write().unwrap() = config.translation.clone();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+
+  use super::*;
+  use crate::contracts::{
+    cache::MockCache,
+    http::{MockHttpClient, PostResponse},
+  };
+
+  fn no_cache() -> Config {
+    Config {
+      cache_ttl: Duration::ZERO,
+      ..Default::default()
+    }
+  }
+
+  #[tokio::test]
+  async fn falls_back_to_the_next_provider() -> Result<()> {
+    let mut http_client = MockHttpClient::new();
+    http_client.expect_get().returning(|_, _| {
+      Ok(contracts::http::GetResponse {
+        headers: Default::default(),
+        body: Bytes::from("<html>rate limited</html>"),
+      })
+    });
+    http_client.expect_post().returning(|url, _, _| {
+      assert_eq!("http://localhost:5000/translate", url);
+      Ok(PostResponse {
+        body: Bytes::from(r#"{"translatedText": "hello"}"#),
+      })
+    });
+
+    let translation = Translation::new(
+      Config {
+        providers: vec![Provider::Google, Provider::Libretranslate],
+        libretranslate: libretranslate::Config {
+          base_url: String::from("http://localhost:5000/"),
+          api_key: String::new(),
+        },
+        ..no_cache()
+      },
+      Arc::new(http_client),
+      Arc::new(MockCache::new()),
+    );
+
+    assert_eq!("hello", translation.translate("olá", "pt", "en").await?);
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn caches_translations() -> Result<()> {
+    let stored = Arc::new(std::sync::Mutex::new(None));
+
+    let mut cache = MockCache::new();
+    let get_stored = Arc::clone(&stored);
+    cache
+      .expect_get()
+      .returning(move |_| Ok(get_stored.lock().unwrap().clone()));
+    cache
+      .expect_put()
+      .times(1)
+      .returning(move |key, value, ttl| {
+        assert_eq!(cache_key(Provider::Google, "pt", "en", "olá"), key);
+        assert_eq!(Some(Config::default().cache_ttl), ttl);
+        *stored.lock().unwrap() = Some(value);
+        Ok(())
+      });
+
+    let mut http_client = MockHttpClient::new();
+    http_client.expect_get().times(1).returning(|_, _| {
+      Ok(contracts::http::GetResponse {
+        headers: Default::default(),
+        body: Bytes::from(r#"[[["hello","olá",null,null,10]],null,"pt"]"#),
+      })
+    });
+
+    let translation = Translation::new(Config::default(), Arc::new(http_client), Arc::new(cache));
+
+    assert_eq!("hello", translation.translate("olá", "pt", "en").await?);
+    assert_eq!("hello", translation.translate("olá", "pt", "en").await?);
+    assert_eq!(CacheStats { hits: 1, misses: 1 }, translation.cache_stats());
+
+    Ok(())
+  }
+}
@@ -0,0 +1,570 @@
+//! Keeps the audio made by the engines in the disk so the same text is not read again.
+//!
+//! Every entry is a directory named after the hash of the engine, voice and text
+//! with one file per audio, `0.mp3`, `1.mp3`, and so on. The audio is played from
+//! memory so entries can be deleted while their audio is waiting to be played.
+
+use std::{
+  collections::HashMap,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+  time::SystemTime,
+};
+
+use anyhow::Result;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use super::Engine;
+use crate::{
+  contracts::{
+    self,
+    tts::{AudioSource, Codec},
+  },
+  utils,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub dir: PathBuf,
+  /// The least recently played audio is deleted when the cache gets bigger than this, 0 disables the cache.
+  pub max_size_mb: u64,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      dir: PathBuf::from("tts_cache"),
+      max_size_mb: 100,
+    }
+  }
+}
+
+impl Config {
+  fn max_size_bytes(&self) -> u64 {
+    self.max_size_mb * 1024 * 1024
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+  pub entries: usize,
+  pub size_bytes: u64,
+  /// How many audios were found in the cache since the bot started.
+  pub hits: u64,
+  pub misses: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+  files: Vec<PathBuf>,
+  size_bytes: u64,
+  last_used: SystemTime,
+}
+
+/// The entries in the cache directory, read from the disk when the cache is first used.
+#[derive(Debug)]
+struct Index {
+  dir: PathBuf,
+  entries: HashMap<String, Entry>,
+}
+
+impl Index {
+  fn size_bytes(&self) -> u64 {
+    self.entries.values().map(|entry| entry.size_bytes).sum()
+  }
+}
+
+pub struct AudioCache {
+  http_client: Arc<dyn contracts::http::HttpClient>,
+  index: Mutex<Option<Index>>,
+  hits: AtomicU64,
+  misses: AtomicU64,
+}
+
+impl AudioCache {
+  pub fn new(http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      http_client,
+      index: Mutex::new(None),
+      hits: AtomicU64::new(0),
+      misses: AtomicU64::new(0),
+    }
+  }
+
+  /// Returns the cached audio of the text read by the engine with the voice.
+  #[tracing::instrument(name = "AudioCache::get", skip_all)]
+  pub async fn get(
+    &self,
+    config: &Config,
+    engine: Engine,
+    voice: &str,
+    text: &str,
+  ) -> Result<Option<Vec<AudioSource>>> {
+    if config.max_size_mb == 0 {
+      return Ok(None);
+    }
+
+    let key = key(engine, voice, text);
+
+    let mut index = self.index.lock().await;
+    let index = load_index(&mut index, config).await?;
+
+    match index.entries.get_mut(&key) {
+      None => {
+        let misses = self.misses.fetch_add(1, Ordering::Relaxed) + 1;
+        info!(
+          "tts cache miss. hits={} misses={}",
+          self.hits.load(Ordering::Relaxed),
+          misses
+        );
+        Ok(None)
+      }
+      Some(entry) => {
+        let hits = self.hits.fetch_add(1, Ordering::Relaxed) + 1;
+        info!(
+          "tts cache hit. hits={} misses={}",
+          hits,
+          self.misses.load(Ordering::Relaxed)
+        );
+
+        entry.last_used = SystemTime::now();
+
+        let mut audio = Vec::with_capacity(entry.files.len());
+        for file in entry.files.iter() {
+          audio.push(AudioSource::Bytes {
+            data: tokio::fs::read(file).await?.into(),
+            codec: codec_of(&file.to_string_lossy()),
+          });
+        }
+
+        // The modification time keeps the order the audio was played in after a restart.
+        let file = entry.files[0].clone();
+        tokio::task::spawn_blocking(move || {
+          if let Err(err) = touch(&file) {
+            error!("unable to touch cached audio. error={:?}", err);
+          }
+        });
+
+        Ok(Some(audio))
+      }
+    }
+  }
+
+  /// Stores the audio and returns it in memory to be played instead of the engine files.
+  /// Audio bigger than the cache is not stored.
+  #[tracing::instrument(name = "AudioCache::insert", skip_all)]
+  pub async fn insert(
+    &self,
+    config: &Config,
+    engine: Engine,
+    voice: &str,
+    text: &str,
+    audio: &[AudioSource],
+  ) -> Result<Vec<AudioSource>> {
+    if config.max_size_mb == 0 || audio.is_empty() {
+      return Ok(audio.to_vec());
+    }
+
+    let key = key(engine, voice, text);
+
+    // Reading the index deletes the temporary directories, so it is read before one is written.
+    load_index(&mut *self.index.lock().await, config).await?;
+
+    // The files are written to a temporary directory so a partial entry is never found.
+    let temporary_dir = config.dir.join(format!(
+      "{}.{:08x}.tmp",
+      key,
+      rand::thread_rng().gen::<u32>()
+    ));
+    let written = self.write_entry(&temporary_dir, audio).await;
+    let cached = match written {
+      Err(err) => {
+        let _ = tokio::fs::remove_dir_all(&temporary_dir).await;
+        return Err(err);
+      }
+      Ok(cached) => cached,
+    };
+
+    let size_bytes = cached
+      .iter()
+      .map(|source| match source {
+        AudioSource::Bytes { data, .. } => data.len() as u64,
+        _ => 0,
+      })
+      .sum();
+    if size_bytes > config.max_size_bytes() {
+      info!(
+        "tts audio is bigger than the cache. size_bytes={}",
+        size_bytes
+      );
+      tokio::fs::remove_dir_all(&temporary_dir).await?;
+      return Ok(audio.to_vec());
+    }
+
+    let mut index = self.index.lock().await;
+    let index = load_index(&mut index, config).await?;
+
+    // The same text may have been read twice at the same time.
+    let entry_dir = config.dir.join(&key);
+    if index.entries.remove(&key).is_some() {
+      tokio::fs::remove_dir_all(&entry_dir).await?;
+    }
+    tokio::fs::rename(&temporary_dir, &entry_dir).await?;
+
+    let files = entry_files(&entry_dir).await?;
+    index.entries.insert(
+      key.clone(),
+      Entry {
+        files,
+        size_bytes,
+        last_used: SystemTime::now(),
+      },
+    );
+
+    evict(index, config.max_size_bytes(), &key).await;
+
+    // The audio in memory is played instead of the temporary file.
+    for source in audio.iter() {
+      if let AudioSource::Path(path) = source {
+        super::remove_audio_file(path.as_os_str()).await;
+      }
+    }
+
+    Ok(cached)
+  }
+
+  /// Writes the audio to the directory and returns it in memory.
+  async fn write_entry(&self, dir: &Path, audio: &[AudioSource]) -> Result<Vec<AudioSource>> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    let mut cached = Vec::with_capacity(audio.len());
+
+    for (i, source) in audio.iter().enumerate() {
+      let (data, codec) = match source {
+        AudioSource::Bytes { data, codec } => (data.clone(), *codec),
+        AudioSource::Path(path) => {
+          let data = tokio::fs::read(path).await?;
+          (data.into(), codec_of(&path.to_string_lossy()))
+        }
+        // The http client returns an error instead of the body of unsuccessful responses.
+        AudioSource::Url(url) => {
+          let response = self.http_client.get(url, None).await?;
+          if response.body.is_empty() {
+            return Err(anyhow::anyhow!("audio url returned no audio. url={}", url));
+          }
+          (response.body, codec_of(url))
+        }
+      };
+
+      tokio::fs::write(dir.join(format!("{}.{}", i, codec.extension())), &data).await?;
+      cached.push(AudioSource::Bytes { data, codec });
+    }
+
+    Ok(cached)
+  }
+
+  #[tracing::instrument(name = "AudioCache::stats", skip_all)]
+  pub async fn stats(&self, config: &Config) -> Result<CacheStats> {
+    let mut index = self.index.lock().await;
+    let index = load_index(&mut index, config).await?;
+
+    Ok(CacheStats {
+      entries: index.entries.len(),
+      size_bytes: index.size_bytes(),
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+    })
+  }
+
+  /// Deletes every cached audio and returns how many were deleted.
+  /// The audio that cannot be deleted is kept in the cache.
+  #[tracing::instrument(name = "AudioCache::clear", skip_all)]
+  pub async fn clear(&self, config: &Config) -> Result<usize> {
+    let mut index = self.index.lock().await;
+    let index = load_index(&mut index, config).await?;
+
+    let keys: Vec<String> = index.entries.keys().cloned().collect();
+
+    let mut deleted = 0;
+    let mut errors = Vec::new();
+    for key in keys {
+      match tokio::fs::remove_dir_all(index.dir.join(&key)).await {
+        Err(err) => errors.push(format!("{}: {}", key, err)),
+        Ok(()) => {
+          index.entries.remove(&key);
+          deleted += 1;
+        }
+      }
+    }
+
+    info!(
+      "tts cache cleared. entries={} errors={}",
+      deleted,
+      errors.len()
+    );
+
+    if !errors.is_empty() {
+      return Err(anyhow::anyhow!(
+        "unable to delete {} cached audios. errors={:?}",
+        errors.len(),
+        errors
+      ));
+    }
+
+    Ok(deleted)
+  }
+}
+
+fn key(engine: Engine, voice: &str, text: &str) -> String {
+  utils::sha256_hex(format!("{}\0{}\0{}", engine, voice, text).as_bytes())
+}
+
+/// Guesses the codec from the file extension, the engines that return urls return mp3.
+fn codec_of(location: &str) -> Codec {
+  let extension = location
+    .rsplit_once('.')
+    .map(|(_, extension)| extension.to_lowercase());
+
+  match extension.as_deref() {
+    Some("wav") => Codec::Wav,
+    Some("ogg") => Codec::Ogg,
+    _ => Codec::Mp3,
+  }
+}
+
+fn touch(path: &Path) -> std::io::Result<()> {
+  std::fs::File::options()
+    .write(true)
+    .open(path)?
+    .set_modified(SystemTime::now())
+}
+
+/// Returns the index of the cache directory, it is read again when the directory changes.
+async fn load_index<'a>(index: &'a mut Option<Index>, config: &Config) -> Result<&'a mut Index> {
+  if index.as_ref().map(|index| &index.dir) != Some(&config.dir) {
+    *index = Some(read_index(&config.dir).await?);
+  }
+
+  Ok(index.as_mut().unwrap())
+}
+
+async fn read_index(dir: &Path) -> Result<Index> {
+  tokio::fs::create_dir_all(dir).await?;
+
+  let mut entries = HashMap::new();
+
+  let mut dir_entries = tokio::fs::read_dir(dir).await?;
+  while let Some(dir_entry) = dir_entries.next_entry().await? {
+    let path = dir_entry.path();
+    let key = dir_entry.file_name().to_string_lossy().into_owned();
+
+    // Left behind when the bot stopped while writing an entry.
+    if key.ends_with(".tmp") {
+      let _ = tokio::fs::remove_dir_all(&path).await;
+      continue;
+    }
+
+    let files = entry_files(&path).await?;
+    if files.is_empty() {
+      continue;
+    }
+
+    let mut size_bytes = 0;
+    let mut last_used = SystemTime::UNIX_EPOCH;
+    for file in files.iter() {
+      let metadata = tokio::fs::metadata(file).await?;
+      size_bytes += metadata.len();
+      last_used = last_used.max(metadata.modified()?);
+    }
+
+    entries.insert(
+      key,
+      Entry {
+        files,
+        size_bytes,
+        last_used,
+      },
+    );
+  }
+
+  info!("tts cache loaded. entries={}", entries.len());
+
+  Ok(Index {
+    dir: dir.to_owned(),
+    entries,
+  })
+}
+
+/// Returns the audio files of an entry in the order they are played.
+async fn entry_files(dir: &Path) -> Result<Vec<PathBuf>> {
+  let mut files = Vec::new();
+
+  let mut dir_entries = tokio::fs::read_dir(dir).await?;
+  while let Some(dir_entry) = dir_entries.next_entry().await? {
+    let path = dir_entry.path();
+    let position = path
+      .file_stem()
+      .and_then(|stem| stem.to_str())
+      .and_then(|stem| stem.parse::<usize>().ok());
+
+    if let Some(position) = position {
+      files.push((position, path));
+    }
+  }
+
+  files.sort();
+
+  Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Deletes the least recently used entries, except `keep`, until the cache fits in `max_size_bytes`.
+async fn evict(index: &mut Index, max_size_bytes: u64, keep: &str) {
+  let mut size_bytes = index.size_bytes();
+
+  while size_bytes > max_size_bytes {
+    let key = match index
+      .entries
+      .iter()
+      .filter(|(key, _)| key.as_str() != keep)
+      .min_by_key(|(_, entry)| entry.last_used)
+      .map(|(key, _)| key.clone())
+    {
+      None => break,
+      Some(key) => key,
+    };
+
+    let entry = index.entries.remove(&key).unwrap();
+    size_bytes -= entry.size_bytes;
+
+    info!(
+      "tts cache eviction. key={} size_bytes={}",
+      key, entry.size_bytes
+    );
+
+    if let Err(err) = tokio::fs::remove_dir_all(index.dir.join(&key)).await {
+      error!("unable to delete cached audio. key={} error={:?}", key, err);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+
+  use super::*;
+  use crate::contracts::http::MockHttpClient;
+
+  fn wav(data: &'static [u8]) -> AudioSource {
+    AudioSource::Bytes {
+      data: Bytes::from_static(data),
+      codec: Codec::Wav,
+    }
+  }
+
+  #[tokio::test]
+  async fn evicts_the_least_recently_used_audio() -> Result<()> {
+    let config = Config {
+      dir: std::env::temp_dir().join(format!("tts_cache_test_{:08x}", rand::random::<u32>())),
+      max_size_mb: 1,
+    };
+    let half_megabyte: &'static [u8] = Box::leak(vec![0; 512 * 1024].into_boxed_slice());
+
+    let cache = AudioCache::new(Arc::new(MockHttpClient::new()));
+
+    assert_eq!(None, cache.get(&config, Engine::Espeak, "pt", "oi").await?);
+
+    let cached = cache
+      .insert(&config, Engine::Espeak, "pt", "oi", &[wav(half_megabyte)])
+      .await?;
+    assert_eq!(
+      Some(cached),
+      cache.get(&config, Engine::Espeak, "pt", "oi").await?
+    );
+    // Another voice is another entry.
+    assert_eq!(None, cache.get(&config, Engine::Espeak, "en", "oi").await?);
+
+    cache
+      .insert(
+        &config,
+        Engine::Espeak,
+        "pt",
+        "tchau",
+        &[wav(half_megabyte)],
+      )
+      .await?;
+    // "oi" was used more recently than "tchau".
+    cache.get(&config, Engine::Espeak, "pt", "oi").await?;
+    cache
+      .insert(&config, Engine::Espeak, "pt", "olá", &[wav(b"RIFF")])
+      .await?;
+
+    assert!(cache
+      .get(&config, Engine::Espeak, "pt", "oi")
+      .await?
+      .is_some());
+    assert!(cache
+      .get(&config, Engine::Espeak, "pt", "olá")
+      .await?
+      .is_some());
+    assert_eq!(
+      None,
+      cache.get(&config, Engine::Espeak, "pt", "tchau").await?
+    );
+
+    // The entries are found again after a restart.
+    let cache = AudioCache::new(Arc::new(MockHttpClient::new()));
+    let stats = cache.stats(&config).await?;
+    assert_eq!(2, stats.entries);
+    assert_eq!(512 * 1024 + 4, stats.size_bytes);
+
+    assert_eq!(2, cache.clear(&config).await?);
+    assert_eq!(0, cache.stats(&config).await?.entries);
+
+    tokio::fs::remove_dir_all(&config.dir).await?;
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn does_not_store_audio_bigger_than_the_cache() -> Result<()> {
+    let config = Config {
+      dir: std::env::temp_dir().join(format!("tts_cache_test_{:08x}", rand::random::<u32>())),
+      max_size_mb: 1,
+    };
+    let megabyte_and_a_half: &'static [u8] = Box::leak(vec![0; 1536 * 1024].into_boxed_slice());
+
+    let cache = AudioCache::new(Arc::new(MockHttpClient::new()));
+
+    cache
+      .insert(&config, Engine::Espeak, "pt", "oi", &[wav(b"RIFF")])
+      .await?;
+
+    let audio = [wav(megabyte_and_a_half)];
+    assert_eq!(
+      audio.to_vec(),
+      cache
+        .insert(&config, Engine::Espeak, "pt", "tchau", &audio)
+        .await?
+    );
+    assert_eq!(
+      None,
+      cache.get(&config, Engine::Espeak, "pt", "tchau").await?
+    );
+    // The audio that fits is not evicted to make room for it.
+    assert!(cache
+      .get(&config, Engine::Espeak, "pt", "oi")
+      .await?
+      .is_some());
+
+    tokio::fs::remove_dir_all(&config.dir).await?;
+
+    Ok(())
+  }
+}
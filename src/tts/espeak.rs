@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::contracts::{
+  self,
+  tts::{AudioSource, Codec},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// The espeak-ng program, a path or a name in `PATH`.
+  pub command: String,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      command: String::from("espeak-ng"),
+    }
+  }
+}
+
+/// Reads text with espeak-ng, voices are like `pt-br`, `espeak-ng --voices` lists them.
+pub struct Espeak {
+  config: Config,
+}
+
+impl Espeak {
+  pub fn new(config: Config) -> Self {
+    Self { config }
+  }
+}
+
+#[async_trait]
+impl contracts::tts::TextToSpeech for Espeak {
+  #[tracing::instrument(name = "Espeak::create_audio", skip_all)]
+  async fn create_audio(&self, text: String, voice: Option<String>) -> Result<Vec<AudioSource>> {
+    let voice = voice.context("espeak needs a voice")?;
+
+    // The text is read from stdin so it is never taken as an option.
+    let wav = super::run(
+      Command::new(&self.config.command)
+        .arg("-v")
+        .arg(voice.to_lowercase())
+        .arg("--stdout")
+        .arg("--stdin"),
+      Some(&text),
+    )
+    .await?;
+
+    Ok(vec![AudioSource::Bytes {
+      data: wav.into(),
+      codec: Codec::Wav,
+    }])
+  }
+
+  #[tracing::instrument(name = "Espeak::voices", skip_all)]
+  async fn voices(&self) -> Result<Vec<String>> {
+    let output = super::run(Command::new(&self.config.command).arg("--voices"), None).await?;
+
+    Ok(parse_voices(&String::from_utf8_lossy(&output)))
+  }
+}
+
+/// Returns the languages in the `espeak-ng --voices` table, they are used as the voice names.
+fn parse_voices(table: &str) -> Vec<String> {
+  table
+    .lines()
+    .skip(1)
+    .filter_map(|line| line.split_whitespace().nth(1))
+    .map(String::from)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_the_voices_table() {
+    let table = "\
+Pty Language       Age/Gender VoiceName          File                 Other Languages
+ 5  af              --/M      Afrikaans          gmw/af
+ 5  en-us           --/M      English_(America)  gmw/en-US            (en 3)
+ 5  pt-br           --/M      Portuguese_(Brazil) roa/pt-BR           (pt 6)
+";
+
+    assert_eq!(vec!["af", "en-us", "pt-br"], parse_voices(table));
+  }
+}
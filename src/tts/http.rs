@@ -0,0 +1,200 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::contracts::{
+  self,
+  http::GetOptions,
+  tts::{AudioSource, Codec},
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// Like `http://localhost:5500/api/tts` for OpenTTS, `text` and `voice` are sent in the query.
+  pub url: String,
+  /// The format of the audio the server returns.
+  pub format: Codec,
+  /// Returns the voices as a JSON array, or an object with the voices as keys like OpenTTS `/api/voices`.
+  pub voices_url: String,
+}
+
+/// Reads text with a text to speech server.
+pub struct Http {
+  config: Config,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+}
+
+impl Http {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      config,
+      http_client,
+    }
+  }
+}
+
+#[async_trait]
+impl contracts::tts::TextToSpeech for Http {
+  #[tracing::instrument(name = "Http::create_audio", skip_all)]
+  async fn create_audio(&self, text: String, voice: Option<String>) -> Result<Vec<AudioSource>> {
+    let voice = voice.context("the tts server needs a voice")?;
+
+    let response = self
+      .http_client
+      .get(
+        &self.config.url,
+        Some(GetOptions {
+          headers: None,
+          query: Some(vec![
+            ("text".to_string(), text),
+            ("voice".to_string(), voice),
+          ]),
+          timeout: Some(Duration::from_secs(60)),
+        }),
+      )
+      .await?;
+
+    // Servers may answer with an error page, it must not be played or cached.
+    let content_type = response
+      .headers
+      .get(CONTENT_TYPE)
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or_default();
+    if !content_type.starts_with("audio/") {
+      return Err(anyhow::anyhow!(
+        "the tts server did not return audio. content_type={:?} body={}",
+        content_type,
+        String::from_utf8_lossy(&response.body[..response.body.len().min(200)])
+      ));
+    }
+
+    info!("audio created. bytes={}", response.body.len());
+
+    Ok(vec![AudioSource::Bytes {
+      data: response.body,
+      codec: self.config.format,
+    }])
+  }
+
+  #[tracing::instrument(name = "Http::voices", skip_all)]
+  async fn voices(&self) -> Result<Vec<String>> {
+    if self.config.voices_url.is_empty() {
+      return Err(anyhow::anyhow!("tts.http.voices_url is not configured"));
+    }
+
+    let response = self
+      .http_client
+      .get(
+        &self.config.voices_url,
+        Some(GetOptions {
+          headers: None,
+          query: None,
+          timeout: Some(Duration::from_secs(30)),
+        }),
+      )
+      .await?;
+
+    let voices = match serde_json::from_slice(&response.body)? {
+      serde_json::Value::Array(voices) => voices
+        .into_iter()
+        .filter_map(|voice| voice.as_str().map(String::from))
+        .collect(),
+      serde_json::Value::Object(voices) => voices.into_iter().map(|(voice, _)| voice).collect(),
+      _ => {
+        return Err(anyhow::anyhow!(
+          "unexpected voices response. response={}",
+          String::from_utf8_lossy(&response.body)
+        ))
+      }
+    };
+
+    Ok(voices)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+
+  use reqwest::header::{HeaderMap, HeaderValue};
+
+  use super::*;
+  use crate::contracts::{http::MockHttpClient, tts::TextToSpeech};
+
+  #[tokio::test]
+  async fn returns_the_audio_sent_by_the_server() -> Result<()> {
+    let mut http_client = MockHttpClient::new();
+    http_client.expect_get().returning(|url, options| {
+      assert_eq!("http://localhost:5500/api/tts", url);
+      assert_eq!(
+        Some(vec![
+          ("text".to_string(), "olá".to_string()),
+          ("voice".to_string(), "pt".to_string()),
+        ]),
+        options.unwrap().query
+      );
+      Ok(contracts::http::GetResponse {
+        headers: audio_headers(),
+        body: Bytes::from_static(b"ID3"),
+      })
+    });
+
+    let engine = Http::new(
+      Config {
+        url: String::from("http://localhost:5500/api/tts"),
+        format: Codec::Mp3,
+        voices_url: String::new(),
+      },
+      Arc::new(http_client),
+    );
+
+    let audio = engine
+      .create_audio(String::from("olá"), Some(String::from("pt")))
+      .await?;
+
+    assert_eq!(
+      vec![AudioSource::Bytes {
+        data: Bytes::from_static(b"ID3"),
+        codec: Codec::Mp3,
+      }],
+      audio
+    );
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn rejects_responses_that_are_not_audio() {
+    let mut http_client = MockHttpClient::new();
+    http_client.expect_get().returning(|_, _| {
+      Ok(contracts::http::GetResponse {
+        headers: Default::default(),
+        body: Bytes::from_static(b"<html>not found</html>"),
+      })
+    });
+
+    let engine = Http::new(
+      Config {
+        url: String::from("http://localhost:5500/api/tts"),
+        ..Default::default()
+      },
+      Arc::new(http_client),
+    );
+
+    assert!(engine
+      .create_audio(String::from("olá"), Some(String::from("pt")))
+      .await
+      .is_err());
+  }
+
+  fn audio_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("audio/mpeg"));
+    headers
+  }
+}
@@ -0,0 +1,432 @@
+//! Text to speech engines and the [Tts] that reads text with the configured engine.
+
+pub mod cache;
+pub mod espeak;
+pub mod http;
+pub mod piper;
+pub mod soundoftext;
+pub mod voice;
+
+use std::{
+  ffi::{OsStr, OsString},
+  path::{Path, PathBuf},
+  sync::{Arc, RwLock},
+};
+
+use anyhow::{Context as _, Result};
+use async_trait::async_trait;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+use tracing::{error, warn};
+
+use crate::{
+  config,
+  contracts::{
+    self,
+    tts::{AudioSource, TextToSpeech},
+  },
+  language,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+  #[default]
+  Soundoftext,
+  /// Runs espeak-ng locally, it works without network.
+  Espeak,
+  /// Runs Piper locally, it works without network.
+  Piper,
+  /// Any server that returns the audio for `GET url?text=...&voice=...`.
+  Http,
+}
+
+impl std::fmt::Display for Engine {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Engine::Soundoftext => "soundoftext",
+      Engine::Espeak => "espeak",
+      Engine::Piper => "piper",
+      Engine::Http => "http",
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub engine: Engine,
+  /// The voice used to read the text when no voice is chosen and the engine has no voice
+  /// for the language of the text, the voice names depend on the engine, like `pt-BR` for soundoftext.
+  pub voice: String,
+  pub espeak: espeak::Config,
+  pub piper: piper::Config,
+  pub http: http::Config,
+  pub cache: cache::Config,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      engine: Engine::default(),
+      voice: String::from("pt-BR"),
+      espeak: espeak::Config::default(),
+      piper: piper::Config::default(),
+      http: http::Config::default(),
+      cache: cache::Config::default(),
+    }
+  }
+}
+
+/// Reads text with the configured engine.
+pub struct Tts {
+  config: RwLock<Config>,
+  client: reqwest::Client,
+  http_client: Arc<dyn contracts::http::HttpClient>,
+  cache: cache::AudioCache,
+}
+
+impl Tts {
+  pub fn new(config: Config, http_client: Arc<dyn contracts::http::HttpClient>) -> Self {
+    Self {
+      config: RwLock::new(config),
+      client: reqwest::Client::new(),
+      cache: cache::AudioCache::new(Arc::clone(&http_client)),
+      http_client,
+    }
+  }
+
+  /// The voice used when no voice is chosen and the engine has no voice for the language of the text.
+  pub fn default_voice(&self) -> String {
+    self.config.read().unwrap().voice.clone()
+  }
+
+  /// Returns the voice as the engine names it, None when the engine does not have it.
+  pub async fn find_voice(&self, voice: &str) -> Option<String> {
+    find_voice(self, voice).await
+  }
+
+  pub async fn cache_stats(&self) -> Result<cache::CacheStats> {
+    let config = self.config.read().unwrap().cache.clone();
+    self.cache.stats(&config).await
+  }
+
+  /// Deletes the cached audio and returns how many texts were deleted.
+  pub async fn clear_cache(&self) -> Result<usize> {
+    let config = self.config.read().unwrap().cache.clone();
+    self.cache.clear(&config).await
+  }
+
+  fn engine(&self) -> Box<dyn TextToSpeech> {
+    let config = self.config.read().unwrap();
+
+    match config.engine {
+      Engine::Soundoftext => Box::new(soundoftext::SoundOfText::new(self.client.clone())),
+      Engine::Espeak => Box::new(espeak::Espeak::new(config.espeak.clone())),
+      Engine::Piper => Box::new(piper::Piper::new(config.piper.clone())),
+      Engine::Http => Box::new(http::Http::new(
+        config.http.clone(),
+        Arc::clone(&self.http_client),
+      )),
+    }
+  }
+}
+
+#[async_trait]
+impl TextToSpeech for Tts {
+  #[tracing::instrument(name = "Tts::create_audio", skip_all)]
+  async fn create_audio(&self, text: String, voice: Option<String>) -> Result<Vec<AudioSource>> {
+    let config = self.config.read().unwrap().clone();
+
+    // The voice may have been chosen before the engine changed, so it is checked again.
+    // Engines that cannot list their voices, like a server without a voices url, use it as it is.
+    let voice = match self.engine().voices().await {
+      Err(err) => {
+        warn!("unable to list the tts voices. error={:?}", err);
+        voice.unwrap_or(config.voice)
+      }
+      Ok(voices) => pick_voice(
+        &voices,
+        voice.as_deref(),
+        language::detect(&text),
+        config.voice,
+      ),
+    };
+
+    match self
+      .cache
+      .get(&config.cache, config.engine, &voice, &text)
+      .await
+    {
+      Err(err) => error!("unable to read the tts cache. error={:?}", err),
+      Ok(Some(audio)) => return Ok(audio),
+      Ok(None) => (),
+    }
+
+    let audio = self
+      .engine()
+      .create_audio(text.clone(), Some(voice.clone()))
+      .await?;
+
+    match self
+      .cache
+      .insert(&config.cache, config.engine, &voice, &text, &audio)
+      .await
+    {
+      Err(err) => {
+        error!("unable to cache tts audio. error={:?}", err);
+        Ok(audio)
+      }
+      Ok(cached) => Ok(cached),
+    }
+  }
+
+  async fn voices(&self) -> Result<Vec<String>> {
+    self.engine().voices().await
+  }
+}
+
+impl config::Reloadable for Tts {
+  fn reload(&self, config: &config::Config) {
+    *self.config.write().unwrap() = config.tts.clone();
+  }
+}
+
+/// Returns the voice as `engine` names it, None when the engine does not have it.
+/// The voice is trusted when the engine cannot list its voices.
+pub async fn find_voice(engine: &dyn TextToSpeech, voice: &str) -> Option<String> {
+  match engine.voices().await {
+    Err(err) => {
+      warn!("unable to list the tts voices. error={:?}", err);
+      Some(voice.to_owned())
+    }
+    Ok(voices) => voices
+      .into_iter()
+      .find(|candidate| candidate.eq_ignore_ascii_case(voice)),
+  }
+}
+
+/// Returns `voice` when the engine has it, then the first voice for `language`,
+/// like `pt-BR` or `pt_BR-faber-medium` for `pt`, then `default_voice`.
+/// `default_voice` is preferred when it is also a voice for `language`.
+fn pick_voice(
+  voices: &[String],
+  voice: Option<&str>,
+  language: Option<&str>,
+  default_voice: String,
+) -> String {
+  if let Some(voice) = voice {
+    match voices
+      .iter()
+      .find(|candidate| candidate.eq_ignore_ascii_case(voice))
+    {
+      Some(voice) => return voice.clone(),
+      None => warn!("the engine no longer has the voice. voice={}", voice),
+    }
+  }
+
+  let language = match language {
+    None => return default_voice,
+    Some(language) => language,
+  };
+
+  let speaks = |voice: &str| {
+    let code = voice.split(['-', '_']).next().unwrap_or_default();
+    code.eq_ignore_ascii_case(language)
+  };
+
+  if speaks(&default_voice) {
+    return default_voice;
+  }
+
+  voices
+    .iter()
+    .find(|voice| speaks(voice))
+    .cloned()
+    .unwrap_or(default_voice)
+}
+
+/// The directory the audio made by the local engines is written to until it is played.
+fn audio_dir() -> PathBuf {
+  std::env::temp_dir().join("urubu_do_pix_tts")
+}
+
+/// Returns a new path in [audio_dir] for an audio file with the extension.
+async fn new_audio_file_path(extension: &str) -> Result<PathBuf> {
+  let dir = audio_dir();
+  tokio::fs::create_dir_all(&dir).await?;
+
+  let name: u64 = rand::thread_rng().gen();
+
+  Ok(dir.join(format!("{:016x}.{}", name, extension)))
+}
+
+/// Returns what ffmpeg reads to play the audio. Audio in memory is written to a
+/// temporary file, [remove_audio_file] deletes it after it is played.
+pub async fn audio_location(source: &AudioSource) -> Result<OsString> {
+  match source {
+    AudioSource::Url(url) => Ok(OsString::from(url)),
+    AudioSource::Path(path) => Ok(path.clone().into_os_string()),
+    AudioSource::Bytes { data, codec } => {
+      let path = new_audio_file_path(codec.extension()).await?;
+      tokio::fs::write(&path, data).await?;
+      Ok(path.into_os_string())
+    }
+  }
+}
+
+/// Deletes the audio file if it is temporary, it is not needed after being played.
+pub async fn remove_audio_file(location: &OsStr) {
+  let path = Path::new(location);
+
+  if !path.starts_with(audio_dir()) {
+    return;
+  }
+
+  if let Err(err) = tokio::fs::remove_file(path).await {
+    error!(
+      "unable to remove audio file. path={:?} error={:?}",
+      path, err
+    );
+  }
+}
+
+/// Runs a text to speech program and returns its output, `stdin` is written to the program input.
+async fn run(command: &mut Command, stdin: Option<&str>) -> Result<Vec<u8>> {
+  command
+    .stdin(std::process::Stdio::piped())
+    .stdout(std::process::Stdio::piped())
+    .stderr(std::process::Stdio::piped())
+    .kill_on_drop(true);
+
+  let mut child = command
+    .spawn()
+    .with_context(|| format!("unable to run {:?}", command))?;
+
+  if let Some(input) = stdin {
+    let mut child_stdin = child.stdin.take().context("program has no stdin")?;
+    child_stdin.write_all(input.as_bytes()).await?;
+  }
+  // Close stdin so the program knows the input has ended.
+  drop(child.stdin.take());
+
+  let output = child.wait_with_output().await?;
+
+  if !output.status.success() {
+    return Err(anyhow::anyhow!(
+      "text to speech program failed. command={:?} status={} stderr={}",
+      command,
+      output.status,
+      String::from_utf8_lossy(&output.stderr)
+    ));
+  }
+
+  Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+  use bytes::Bytes;
+
+  use super::*;
+  use crate::contracts::{
+    http::{GetResponse, MockHttpClient},
+    tts::Codec,
+  };
+
+  #[tokio::test]
+  async fn writes_audio_in_memory_to_a_temporary_file() -> Result<()> {
+    let location = audio_location(&AudioSource::Bytes {
+      data: Bytes::from_static(b"RIFF"),
+      codec: Codec::Wav,
+    })
+    .await?;
+
+    assert!(Path::new(&location).starts_with(audio_dir()));
+    assert_eq!(Some("wav".as_ref()), Path::new(&location).extension());
+    assert_eq!(b"RIFF".to_vec(), tokio::fs::read(&location).await?);
+
+    remove_audio_file(&location).await;
+    assert!(!Path::new(&location).exists());
+
+    let url = audio_location(&AudioSource::Url(String::from("https://a.b/c.mp3"))).await?;
+    assert_eq!("https://a.b/c.mp3", url);
+    // Only temporary files are removed.
+    remove_audio_file(&url).await;
+
+    Ok(())
+  }
+
+  #[tokio::test]
+  async fn uses_the_chosen_voice_when_the_engine_cannot_list_its_voices() -> Result<()> {
+    let voices_read = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+    let mut http_client = MockHttpClient::new();
+    let read = Arc::clone(&voices_read);
+    http_client.expect_get().returning(move |_, options| {
+      let query = options.unwrap().query.unwrap();
+      read.lock().unwrap().push(query[1].1.clone());
+
+      let mut headers = reqwest::header::HeaderMap::new();
+      headers.insert(
+        reqwest::header::CONTENT_TYPE,
+        reqwest::header::HeaderValue::from_static("audio/wav"),
+      );
+      Ok(GetResponse {
+        headers,
+        body: Bytes::from_static(b"RIFF"),
+      })
+    });
+
+    // Without a voices url the server voices cannot be listed.
+    let tts = Tts::new(
+      Config {
+        engine: Engine::Http,
+        http: http::Config {
+          url: String::from("http://localhost:5500/api/tts"),
+          ..Default::default()
+        },
+        cache: cache::Config {
+          max_size_mb: 0,
+          ..Default::default()
+        },
+        ..Default::default()
+      },
+      Arc::new(http_client),
+    );
+
+    assert_eq!(Some(String::from("en-US")), tts.find_voice("en-US").await);
+
+    tts
+      .create_audio(String::from("hello there"), Some(String::from("en-US")))
+      .await?;
+    tts.create_audio(String::from("hello there"), None).await?;
+
+    assert_eq!(
+      vec![String::from("en-US"), String::from("pt-BR")],
+      *voices_read.lock().unwrap()
+    );
+
+    Ok(())
+  }
+
+  #[test]
+  fn picks_the_voice_for_the_language() {
+    let voices: Vec<String> = ["en-US", "pt-BR", "pt-PT", "pt_BR-faber-medium"]
+      .iter()
+      .map(|voice| voice.to_string())
+      .collect();
+    let pick = |voice, language, default_voice: &str| {
+      pick_voice(&voices, voice, language, default_voice.to_owned())
+    };
+
+    assert_eq!("pt-PT", pick(Some("PT-pt"), Some("en"), "pt-BR"));
+    // The engine changed and no longer has the chosen voice.
+    assert_eq!("en-US", pick(Some("fr-FR"), Some("en"), "pt-BR"));
+    assert_eq!("pt-BR", pick(Some("fr-FR"), None, "pt-BR"));
+    assert_eq!("pt-PT", pick(None, Some("pt"), "pt-PT"));
+    assert_eq!("pt-BR", pick(None, Some("pt"), "en-US"));
+    assert_eq!("en-US", pick(None, Some("ja"), "en-US"));
+  }
+}
@@ -0,0 +1,116 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::contracts::{self, tts::AudioSource};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// The piper program, a path or a name in `PATH`.
+  pub command: String,
+  /// The directory with the voice models, the voice `pt_BR-faber-medium`
+  /// uses `pt_BR-faber-medium.onnx` and its `.onnx.json` file.
+  pub models_dir: PathBuf,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      command: String::from("piper"),
+      models_dir: PathBuf::from("voices"),
+    }
+  }
+}
+
+/// Reads text with Piper, a neural text to speech that runs locally.
+pub struct Piper {
+  config: Config,
+}
+
+impl Piper {
+  pub fn new(config: Config) -> Self {
+    Self { config }
+  }
+}
+
+#[async_trait]
+impl contracts::tts::TextToSpeech for Piper {
+  #[tracing::instrument(name = "Piper::create_audio", skip_all)]
+  async fn create_audio(&self, text: String, voice: Option<String>) -> Result<Vec<AudioSource>> {
+    let voice = voice.context("piper needs a voice")?;
+    // The voice is a file name in the models directory, it must not reach other directories.
+    if voice.is_empty() || voice.contains(['/', '\\']) || voice.contains("..") {
+      return Err(anyhow::anyhow!("invalid piper voice. voice={:?}", voice));
+    }
+    let model = self.config.models_dir.join(format!("{}.onnx", voice));
+    if !model.exists() {
+      return Err(anyhow::anyhow!(
+        "piper voice model not found. path={:?}",
+        model
+      ));
+    }
+
+    let path = super::new_audio_file_path("wav").await?;
+
+    super::run(
+      Command::new(&self.config.command)
+        .arg("--model")
+        .arg(&model)
+        .arg("--output_file")
+        .arg(&path),
+      // Piper reads one line at a time.
+      Some(&text.replace('\n', " ")),
+    )
+    .await?;
+
+    Ok(vec![AudioSource::Path(path)])
+  }
+
+  #[tracing::instrument(name = "Piper::voices", skip_all)]
+  async fn voices(&self) -> Result<Vec<String>> {
+    let mut voices = Vec::new();
+
+    let mut entries = tokio::fs::read_dir(&self.config.models_dir)
+      .await
+      .with_context(|| format!("unable to read {:?}", self.config.models_dir))?;
+    while let Some(entry) = entries.next_entry().await? {
+      let path = entry.path();
+      if path.extension().and_then(|extension| extension.to_str()) == Some("onnx") {
+        if let Some(voice) = path.file_stem().and_then(|stem| stem.to_str()) {
+          voices.push(voice.to_owned());
+        }
+      }
+    }
+
+    voices.sort();
+
+    Ok(voices)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::contracts::tts::TextToSpeech;
+
+  #[tokio::test]
+  async fn voices_cannot_leave_the_models_dir() {
+    let piper = Piper::new(Config::default());
+
+    for voice in ["../secret", "a/b", "a\\b", ".."] {
+      let err = piper
+        .create_audio(String::from("olá"), Some(String::from(voice)))
+        .await
+        .unwrap_err();
+      assert!(
+        err.to_string().contains("invalid piper voice"),
+        "voice={}",
+        voice
+      );
+    }
+  }
+}
@@ -1,11 +1,84 @@
 use std::{fmt::Write, time::Duration};
 
-use crate::contracts;
+use crate::contracts::{self, tts::AudioSource};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
+/// The voices soundoftext.com offers.
+const VOICES: &[&str] = &[
+  "af-ZA",
+  "ar",
+  "bg",
+  "bn",
+  "bs",
+  "ca-ES",
+  "cmn-Hans-CN",
+  "cmn-Hant-TW",
+  "cs-CZ",
+  "cy",
+  "da-DK",
+  "de-DE",
+  "el-GR",
+  "en-AU",
+  "en-GB",
+  "en-IN",
+  "en-US",
+  "eo",
+  "es-ES",
+  "es-MX",
+  "es-US",
+  "et",
+  "fi-FI",
+  "fil-PH",
+  "fr-CA",
+  "fr-FR",
+  "gu",
+  "hi-IN",
+  "hr-HR",
+  "hu-HU",
+  "hy",
+  "id-ID",
+  "is-IS",
+  "it-IT",
+  "ja-JP",
+  "jv",
+  "km",
+  "kn",
+  "ko-KR",
+  "la",
+  "lv",
+  "mk",
+  "ml",
+  "mr",
+  "ms",
+  "my",
+  "nb-NO",
+  "ne",
+  "nl-NL",
+  "pl-PL",
+  "pt-BR",
+  "pt-PT",
+  "ro-RO",
+  "ru-RU",
+  "si",
+  "sk-SK",
+  "sq",
+  "sr-RS",
+  "su",
+  "sv-SE",
+  "sw",
+  "ta",
+  "te",
+  "th-TH",
+  "tr-TR",
+  "uk-UA",
+  "ur",
+  "vi-VN",
+  "yue-Hant-HK",
+];
+
 #[derive(Debug, Serialize)]
 struct CreateSoundRequest {
   pub data: CreateSoundRequestData,
@@ -29,24 +102,23 @@ struct GetSoundLocationResponse {
   pub location: Option<String>,
 }
 
-pub struct Tts {
+/// Reads text with the Google voices through soundoftext.com, voices are like `pt-BR`.
+pub struct SoundOfText {
   client: reqwest::Client,
 }
 
-impl Tts {
-  pub fn new() -> Self {
-    Self {
-      client: reqwest::Client::new(),
-    }
+impl SoundOfText {
+  pub fn new(client: reqwest::Client) -> Self {
+    Self { client }
   }
 
-  #[tracing::instrument(skip_all, fields(text = %text))]
-  async fn generate_audio(&self, text: String) -> Result<String> {
+  #[tracing::instrument(skip_all, fields(text = %text, voice = %voice))]
+  async fn generate_audio(&self, text: String, voice: &str) -> Result<String> {
     let body = CreateSoundRequest {
       engine: String::from("google"),
       data: CreateSoundRequestData {
         text,
-        voice: String::from("pt-BR"),
+        voice: voice.to_owned(),
       },
     };
 
@@ -111,18 +183,29 @@ impl Tts {
 }
 
 #[async_trait]
-impl contracts::tts::TextToSpeech for Tts {
-  /// Creates a mp3 file containing `text` and returns its url.
-  #[tracing::instrument(skip_all)]
-  async fn create_audio(&self, text: String) -> Result<Vec<String>> {
+impl contracts::tts::TextToSpeech for SoundOfText {
+  /// Creates mp3 files containing `text` and returns their urls.
+  #[tracing::instrument(name = "SoundOfText::create_audio", skip_all)]
+  async fn create_audio(&self, text: String, voice: Option<String>) -> Result<Vec<AudioSource>> {
+    let voice = voice.context("soundoftext needs a voice")?;
+
     let chunks = divide_text_into_chunks(&text)?;
 
     info!("divided text in chunks. chunks={:?}", &chunks);
 
-    futures::future::join_all(chunks.into_iter().map(|chunk| self.generate_audio(chunk)))
-      .await
-      .into_iter()
-      .collect::<Result<_, _>>()
+    futures::future::join_all(
+      chunks
+        .into_iter()
+        .map(|chunk| self.generate_audio(chunk, &voice)),
+    )
+    .await
+    .into_iter()
+    .map(|result| result.map(AudioSource::Url))
+    .collect()
+  }
+
+  async fn voices(&self) -> Result<Vec<String>> {
+    Ok(VOICES.iter().map(|voice| voice.to_string()).collect())
   }
 }
 
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serenity::model::id::{GuildId, UserId};
+
+use crate::{contracts, guild_settings::GuildSettingsStore};
+
+/// Keeps the voices chosen by the guilds and users.
+pub struct VoiceStore {
+  cache: Arc<dyn contracts::cache::Cache>,
+  settings: Arc<GuildSettingsStore>,
+}
+
+impl VoiceStore {
+  pub fn new(cache: Arc<dyn contracts::cache::Cache>, settings: Arc<GuildSettingsStore>) -> Self {
+    Self { cache, settings }
+  }
+
+  /// Returns the voice that reads text for the user. The voice the user chose comes first,
+  /// then the persona voice and the guild voice. None means the engine voice for the
+  /// language of the text, or the configured voice.
+  #[tracing::instrument(name = "VoiceStore::voice", skip_all)]
+  pub async fn voice(
+    &self,
+    guild_id: Option<GuildId>,
+    user_id: UserId,
+    persona_voice: Option<String>,
+  ) -> Result<Option<String>> {
+    if let Some(voice) = self.user_voice(user_id).await? {
+      return Ok(Some(voice));
+    }
+
+    if persona_voice.is_some() {
+      return Ok(persona_voice);
+    }
+
+    self.guild_voice(guild_id).await
+  }
+
+  pub async fn user_voice(&self, user_id: UserId) -> Result<Option<String>> {
+    let voice = self
+      .cache
+      .get(&user_cache_key(user_id))
+      .await?
+      .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+
+    // An empty voice is stored when the user goes back to the default voice.
+    Ok(voice.filter(|voice| !voice.is_empty()))
+  }
+
+  /// Changes the user voice, None goes back to the guild voice.
+  pub async fn set_user_voice(&self, user_id: UserId, voice: Option<String>) -> Result<()> {
+    self
+      .cache
+      .put(
+        user_cache_key(user_id),
+        voice.unwrap_or_default().into_bytes(),
+        None,
+      )
+      .await
+  }
+
+  pub async fn guild_voice(&self, guild_id: Option<GuildId>) -> Result<Option<String>> {
+    match guild_id {
+      None => Ok(None),
+      Some(guild_id) => Ok(self.settings.get(guild_id).await?.tts_voice),
+    }
+  }
+
+  /// Changes the guild voice, None goes back to the configured voice.
+  pub async fn set_guild_voice(&self, guild_id: GuildId, voice: Option<String>) -> Result<()> {
+    self
+      .settings
+      .update(guild_id, |settings| settings.tts_voice = voice)
+      .await?;
+
+    Ok(())
+  }
+}
+
+fn user_cache_key(user_id: UserId) -> Vec<u8> {
+  format!("tts:voice:{}", user_id).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::contracts::cache;
+
+  #[tokio::test]
+  async fn the_user_voice_comes_before_the_persona_and_guild_voices() -> Result<()> {
+    let cache: Arc<dyn contracts::cache::Cache> = Arc::new(cache::in_memory(Arc::default()));
+
+    let voices = VoiceStore::new(
+      Arc::clone(&cache),
+      Arc::new(GuildSettingsStore::new(Arc::clone(&cache))),
+    );
+
+    let guild_id = Some(GuildId(1));
+    let user_id = UserId(2);
+    let persona_voice = || Some(String::from("en-GB"));
+
+    assert_eq!(None, voices.voice(guild_id, user_id, None).await?);
+
+    voices
+      .set_guild_voice(GuildId(1), Some(String::from("pt-PT")))
+      .await?;
+    assert_eq!(
+      Some(String::from("pt-PT")),
+      voices.voice(guild_id, user_id, None).await?
+    );
+    assert_eq!(None, voices.voice(None, user_id, None).await?);
+    assert_eq!(
+      persona_voice(),
+      voices.voice(guild_id, user_id, persona_voice()).await?
+    );
+
+    voices
+      .set_user_voice(user_id, Some(String::from("en-US")))
+      .await?;
+    assert_eq!(
+      Some(String::from("en-US")),
+      voices.voice(guild_id, user_id, persona_voice()).await?
+    );
+
+    voices.set_user_voice(user_id, None).await?;
+    assert_eq!(
+      Some(String::from("pt-PT")),
+      voices.voice(guild_id, user_id, None).await?
+    );
+
+    Ok(())
+  }
+}
@@ -1,4 +1,3 @@
-use anyhow::{Context, Result};
 use serenity::model::channel::Message;
 use tracing::error;
 
@@ -8,8 +7,13 @@ pub fn check_message(res: serenity::Result<Message>) {
   }
 }
 
-pub fn env_key(key: &str) -> Result<String> {
-  std::env::var(key)
-    .ok()
-    .context(format!("missing env variable: {}", key))
+/// Returns the SHA-256 of the bytes in hex, used in keys where a collision
+/// would return the value of another key.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+  use sha2::Digest;
+
+  sha2::Sha256::digest(bytes)
+    .iter()
+    .map(|byte| format!("{:02x}", byte))
+    .collect()
 }
@@ -1,6 +1,9 @@
-use crate::contracts;
-use anyhow::Result;
-use serenity::{model::prelude::Message, prelude::Context};
+use crate::{commands::Invocation, contracts};
+use anyhow::{anyhow, Result};
+use serenity::{
+  model::prelude::{ChannelId, GuildId},
+  prelude::Context,
+};
 use std::{
   collections::VecDeque,
   sync::{Arc, Weak},
@@ -16,7 +19,10 @@ pub struct Video {
 }
 
 struct VideoRequest {
-  msg: Message,
+  /// The guild where the video was requested.
+  guild_id: GuildId,
+  /// The channel where the video was requested.
+  channel_id: ChannelId,
   url: String,
 }
 
@@ -34,21 +40,26 @@ impl Video {
   }
 
   #[tracing::instrument(name = "Video::play", skip_all, fields(url = %url))]
-  pub async fn play(&self, ctx: &Context, msg: &Message, url: &str) -> Result<()> {
+  pub async fn play(&self, ctx: &Context, invocation: &Invocation, url: &str) -> Result<()> {
+    let guild_id = invocation
+      .guild_id()
+      .ok_or_else(|| anyhow!("videos can only be played in a guild"))?;
+
     let mut queue = self.queue.lock().await;
 
     queue.push_back(VideoRequest {
-      msg: msg.clone(),
+      guild_id,
+      channel_id: invocation.channel_id(),
       url: url.to_owned(),
     });
 
-    msg.reply(ctx, "Added to queue").await?;
+    invocation.reply(ctx, "Added to queue").await?;
 
     Ok(())
   }
 
   #[tracing::instrument(name = "Video::skip_current_video", skip_all)]
-  pub async fn skip_current_video(&self, _ctx: &Context, _msg: &Message) -> Result<()> {
+  pub async fn skip_current_video(&self, _ctx: &Context, _invocation: &Invocation) -> Result<()> {
     // Should work with normal videos and playlists.
     // Can we just seek to the last second in the video and let the youtube frame api handle it?
     // player.seekTo(seconds:Number, allowSeekAhead:Boolean):Void
@@ -68,7 +79,11 @@ impl Video {
       if let Some(video_request) = queue.pop_front() {
         self
           .browser
-          .play_video_on_discord(&video_request.msg, &video_request.url)
+          .play_video_on_discord(
+            video_request.guild_id,
+            video_request.channel_id,
+            &video_request.url,
+          )
           .await?;
       }
     }
@@ -1,39 +1,61 @@
 //! This web server is run so we are able to register a service worker in the html page served by it.
 
+use std::{
+  path::{Path, PathBuf},
+  sync::Arc,
+};
+
 use axum::{
   body::{boxed, Body, BoxBody},
   http::{Request, Response, Uri},
   routing::get,
-  Router,
+  Extension, Router,
 };
 use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 
 use tower::util::ServiceExt;
 use tower_http::services::ServeDir;
 
-pub fn router() -> Router {
-  Router::new().nest("/static", get(handler))
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub port: u16,
+  /// The directory with the files served at `/static`, relative to the current directory.
+  pub assets_dir: PathBuf,
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      port: 0,
+      assets_dir: PathBuf::from("src/video_stream_api/assets"),
+    }
+  }
+}
+
+pub fn router(config: &Config) -> Router {
+  Router::new()
+    .nest("/static", get(handler))
+    .layer(Extension(Arc::new(config.assets_dir.clone())))
 }
 
 #[tracing::instrument(name = "GET /static", skip_all, fields(uri = ?uri))]
-async fn handler(uri: Uri) -> Result<Response<BoxBody>, (StatusCode, String)> {
-  get_static_file(uri.clone()).await
+async fn handler(
+  uri: Uri,
+  Extension(assets_dir): Extension<Arc<PathBuf>>,
+) -> Result<Response<BoxBody>, (StatusCode, String)> {
+  get_static_file(uri.clone(), &assets_dir).await
 }
 
-async fn get_static_file(uri: Uri) -> Result<Response<BoxBody>, (StatusCode, String)> {
+async fn get_static_file(
+  uri: Uri,
+  assets_dir: &Path,
+) -> Result<Response<BoxBody>, (StatusCode, String)> {
   let req = Request::builder().uri(uri).body(Body::empty()).unwrap();
 
-  let project_dir = std::env::current_dir()
-    .unwrap()
-    .to_str()
-    .map(String::from)
-    .expect("project dir is required");
-
   // `ServeDir` implements `tower::Service` so we can call it with `tower::ServiceExt::oneshot`
-  match ServeDir::new(format!("{project_dir}/src/video_stream_api/assets"))
-    .oneshot(req)
-    .await
-  {
+  match ServeDir::new(assets_dir).oneshot(req).await {
     Ok(res) => Ok(res.map(boxed)),
     Err(err) => Err((
       StatusCode::INTERNAL_SERVER_ERROR,